@@ -0,0 +1,96 @@
+//the vr4300's primary caches
+//both are direct mapped, virtually indexed and physically tagged. a tag is bits 31..12 of the
+//physical address, the rest of the line address comes from the index
+//the caches only hold data here, filling and writing lines back is up to whoever owns the bus
+
+pub const ICACHE_LINE: usize = 32;
+pub const DCACHE_LINE: usize = 16;
+const LINES: usize = 512;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ICacheLine {
+    pub valid: bool,
+    pub tag: u32,
+    pub data: [u8; ICACHE_LINE],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DCacheLine {
+    pub valid: bool,
+    pub dirty: bool,
+    pub tag: u32,
+    pub data: [u8; DCACHE_LINE],
+}
+
+//16KB, 32 byte lines
+pub struct ICache {
+    pub lines: Vec<ICacheLine>,
+}
+
+//8KB, 16 byte lines, write back
+pub struct DCache {
+    pub lines: Vec<DCacheLine>,
+}
+
+impl Default for ICache {
+    fn default() -> Self {
+        ICache {
+            lines: vec![
+                ICacheLine {
+                    valid: false,
+                    tag: 0,
+                    data: [0; ICACHE_LINE],
+                };
+                LINES
+            ],
+        }
+    }
+}
+
+impl Default for DCache {
+    fn default() -> Self {
+        DCache {
+            lines: vec![
+                DCacheLine {
+                    valid: false,
+                    dirty: false,
+                    tag: 0,
+                    data: [0; DCACHE_LINE],
+                };
+                LINES
+            ],
+        }
+    }
+}
+
+impl ICache {
+    pub fn index(vaddr: u64) -> usize {
+        (vaddr as usize / ICACHE_LINE) % LINES
+    }
+
+    pub fn hit(&self, vaddr: u64, phys: u32) -> bool {
+        let line = &self.lines[Self::index(vaddr)];
+        line.valid && line.tag == phys >> 12
+    }
+
+    //physical address of the start of the line at index
+    pub fn line_addr(&self, index: usize) -> u32 {
+        (self.lines[index].tag << 12) | ((index * ICACHE_LINE) as u32 & 0xFFF)
+    }
+}
+
+impl DCache {
+    pub fn index(vaddr: u64) -> usize {
+        (vaddr as usize / DCACHE_LINE) % LINES
+    }
+
+    pub fn hit(&self, vaddr: u64, phys: u32) -> bool {
+        let line = &self.lines[Self::index(vaddr)];
+        line.valid && line.tag == phys >> 12
+    }
+
+    //physical address of the start of the line at index
+    pub fn line_addr(&self, index: usize) -> u32 {
+        (self.lines[index].tag << 12) | ((index * DCACHE_LINE) as u32 & 0xFFF)
+    }
+}
@@ -1,5 +1,8 @@
+use crate::cache::{DCache, ICache};
 use crate::cart::Cart;
 use crate::rdram::Rdram;
+use crate::tlb::Tlb;
+use log::debug;
 use proc_bitfield::bitfield;
 use std::cell::RefCell;
 use std::ops::{Index, IndexMut};
@@ -13,6 +16,27 @@ pub struct Cpu {
     pub cop0: cop0,
     //needs cop1 (fp coprocessor)
 
+    //there is no cop2 on the vr4300, but its moves still read and write this
+    pub cop2_latch: u64,
+
+    //joint tlb, driven through cop0
+    pub tlb: Tlb,
+    //primary caches
+    pub icache: ICache,
+    pub dcache: DCache,
+
+    //rf.PC is always the instruction being executed, these track what comes after it
+    //address of the instruction that runs once the one at PC retires
+    pub next_pc: u64,
+    //true if the instruction at PC is sitting in a branch delay slot
+    pub in_delay_slot: bool,
+    //set by a branch as it executes, the instruction after it is its delay slot
+    delay_slot_next: bool,
+    //where a taken branch goes once its delay slot has run
+    branch_target: Option<u64>,
+    //Count only ticks every other pcycle, this holds the odd one left over
+    count_remainder: u64,
+
     //////////////////////////////////
     //Rcs to other pieces of the system that we can touch
     mem: Rc<RefCell<Rdram>>,
@@ -21,10 +45,458 @@ pub struct Cpu {
 
 impl Cpu {
     pub fn new(mem: Rc<RefCell<Rdram>>, cart: Rc<RefCell<Cart>>) -> Self {
-        Self {
+        let mut cpu = Self {
             mem,
             cart,
             ..Default::default()
+        };
+        //vr4300 revision 2.2
+        cpu.cop0.PRId = 0x0B22.into();
+        cpu.cop0.Random = 31;
+        //vr4300 fpu, implementation 0x0A revision 0
+        cpu.rf.FCR0 = 0x0A00;
+        cpu
+    }
+
+    //MFC0/DMFC0 view of a cop0 register. 32 bit registers come back zero extended,
+    //MFC0 is in charge of sign extending whatever it gets
+    pub fn read_cop0(&self, reg: cop0reg) -> u64 {
+        let cop0 = &self.cop0;
+        match reg {
+            cop0reg::Index => cop0.Index as u64,
+            cop0reg::Random => cop0.Random as u64,
+            cop0reg::EntryLo0 => cop0.EntryLo0,
+            cop0reg::EntryLo1 => cop0.EntryLo1,
+            cop0reg::Context => cop0.Context,
+            cop0reg::PageMask => cop0.PageMask,
+            cop0reg::Wired => cop0.Wired as u64,
+            cop0reg::BadVAddr => cop0.BadVAddr,
+            cop0reg::Count => cop0.Count as u64,
+            cop0reg::EntryHi => cop0.EntryHi,
+            cop0reg::Compare => cop0.Compare as u64,
+            cop0reg::Status => u32::from(cop0.Status) as u64,
+            cop0reg::Cause => u32::from(cop0.Cause) as u64,
+            cop0reg::EPC => cop0.EPC,
+            cop0reg::PRId => u32::from(cop0.PRId) as u64,
+            cop0reg::Config => u32::from(cop0.Config) as u64,
+            cop0reg::LLAddr => cop0.LLAddr as u64,
+            cop0reg::WatchLo => cop0.WatchLo as u64,
+            cop0reg::WatchHi => cop0.WatchHi as u64,
+            cop0reg::XContext => u64::from(cop0.XContext),
+            cop0reg::Parity => cop0.Parity as u64,
+            cop0reg::Cache => cop0.Cache as u64,
+            cop0reg::TagLo => u64::from(cop0.TagLo),
+            cop0reg::TagHi => cop0.TagHi as u64,
+            cop0reg::ErrorEPC => cop0.ErrorEPC,
+            cop0reg::Reserved7
+            | cop0reg::Reserved21
+            | cop0reg::Reserved22
+            | cop0reg::Reserved23
+            | cop0reg::Reserved24
+            | cop0reg::Reserved25
+            | cop0reg::Reserved31 => cop0.latch,
+        }
+    }
+
+    //MTC0/DMTC0 view of a cop0 register. only the writable bits of each register change,
+    //read only registers just update the latch
+    pub fn write_cop0(&mut self, reg: cop0reg, val: u64) {
+        self.cop0.latch = val;
+
+        //keeps the bits outside of mask, takes the bits inside it from val
+        fn masked(old: u64, val: u64, mask: u64) -> u64 {
+            (old & !mask) | (val & mask)
+        }
+
+        let cop0 = &mut self.cop0;
+        match reg {
+            //the probe failure bit is only set by TLBP
+            cop0reg::Index => cop0.Index = masked(cop0.Index as u64, val, 0x3F) as u32,
+            cop0reg::Random => {}
+            //PFN, C, D, V, G
+            cop0reg::EntryLo0 => cop0.EntryLo0 = val & 0x3FFF_FFFF,
+            cop0reg::EntryLo1 => cop0.EntryLo1 = val & 0x3FFF_FFFF,
+            //only PTEBase, BadVPN2 is filled in by tlb exceptions
+            cop0reg::Context => cop0.Context = masked(cop0.Context, val, 0xFFFF_FFFF_FF80_0000),
+            cop0reg::PageMask => cop0.PageMask = val & 0x01FF_E000,
+            //writing Wired resets Random back to the top
+            cop0reg::Wired => {
+                cop0.Wired = (val & 0x3F) as u32;
+                cop0.Random = 31;
+            }
+            cop0reg::BadVAddr => {}
+            cop0reg::Count => cop0.Count = val as u32,
+            //R, VPN2, ASID
+            cop0reg::EntryHi => cop0.EntryHi = val & 0xC000_00FF_FFFF_E0FF,
+            cop0reg::Compare => self.write_compare(val as u32),
+            //bits 23 and 19 are hardwired to 0 and TS is only set by the tlb
+            cop0reg::Status => {
+                cop0.Status =
+                    (masked(u32::from(cop0.Status) as u64, val, 0xFF57_FFFF) as u32).into()
+            }
+            //only the two software interrupt bits are writable
+            cop0reg::Cause => {
+                cop0.Cause = (masked(u32::from(cop0.Cause) as u64, val, 0x300) as u32).into()
+            }
+            cop0reg::EPC => cop0.EPC = val,
+            cop0reg::PRId => {}
+            //EP, BE, CU and K0. everything else is hardwired
+            cop0reg::Config => {
+                cop0.Config =
+                    (masked(u32::from(cop0.Config) as u64, val, 0x0F00_800F) as u32).into()
+            }
+            cop0reg::LLAddr => cop0.LLAddr = val as u32,
+            //PAddr0, R and W
+            cop0reg::WatchLo => cop0.WatchLo = (val & 0xFFFF_FFFB) as u32,
+            //PAddr1
+            cop0reg::WatchHi => cop0.WatchHi = (val & 0xF) as u32,
+            //only PTEBase, the rest is filled in by tlb exceptions
+            cop0reg::XContext => {
+                cop0.XContext = masked(u64::from(cop0.XContext), val, 0xFFFF_FFFE_0000_0000).into()
+            }
+            cop0reg::Parity => cop0.Parity = (val & 0xFF) as u32,
+            cop0reg::Cache => {}
+            //PTagLo and PState
+            cop0reg::TagLo => cop0.TagLo = (val & 0x0FFF_FFC0).into(),
+            cop0reg::TagHi => {}
+            cop0reg::ErrorEPC => cop0.ErrorEPC = val,
+            cop0reg::Reserved7
+            | cop0reg::Reserved21
+            | cop0reg::Reserved22
+            | cop0reg::Reserved23
+            | cop0reg::Reserved24
+            | cop0reg::Reserved25
+            | cop0reg::Reserved31 => {}
+        }
+    }
+
+    //cop1 register views
+    //with Status.FR set there are 32 full 64 bit fprs. with it clear only the 16 even ones exist,
+    //and an odd word register is the upper half of the even register below it
+    pub fn read_fpr_word(&self, reg: u8) -> u32 {
+        let reg = reg as usize;
+        if !self.cop0.Status.FR() && reg & 1 == 1 {
+            (self.rf.fprs[reg & !1] >> 32) as u32
+        } else {
+            self.rf.fprs[reg] as u32
+        }
+    }
+
+    pub fn write_fpr_word(&mut self, reg: u8, val: u32) {
+        let reg = reg as usize;
+        if !self.cop0.Status.FR() && reg & 1 == 1 {
+            let fpr = &mut self.rf.fprs[reg & !1];
+            *fpr = (*fpr & 0xFFFF_FFFF) | (val as u64) << 32;
+        } else {
+            let fpr = &mut self.rf.fprs[reg];
+            *fpr = (*fpr & 0xFFFF_FFFF_0000_0000) | val as u64;
+        }
+    }
+
+    //doubleword accesses to an odd register land on the even one below it when FR is clear
+    pub fn read_fpr_dword(&self, reg: u8) -> u64 {
+        if self.cop0.Status.FR() {
+            self.rf.fprs[reg as usize]
+        } else {
+            self.rf.fprs[reg as usize & !1]
+        }
+    }
+
+    pub fn write_fpr_dword(&mut self, reg: u8, val: u64) {
+        if self.cop0.Status.FR() {
+            self.rf.fprs[reg as usize] = val;
+        } else {
+            self.rf.fprs[reg as usize & !1] = val;
+        }
+    }
+
+    //CFC1. only FCR0 and FCR31 exist, the rest read as 0
+    pub fn read_fcr(&self, reg: u8) -> u32 {
+        match reg {
+            0 => self.rf.FCR0,
+            31 => u64::from(self.rf.FCR31) as u32,
+            _ => 0,
+        }
+    }
+
+    //CTC1. FCR0 is read only
+    pub fn write_fcr(&mut self, reg: u8, val: u32) {
+        if reg == 31 {
+            //bits 31..25 and 22..18 are hardwired to 0
+            self.rf.FCR31 = ((val & 0x0183_FFFF) as u64).into();
+        }
+    }
+
+    //hard set PC, throwing away any branch that was in flight
+    pub fn set_pc(&mut self, addr: u64) {
+        self.rf.PC = addr;
+        self.next_pc = addr.wrapping_add(4);
+        self.in_delay_slot = false;
+        self.delay_slot_next = false;
+        self.branch_target = None;
+    }
+
+    //a taken branch or jump. the delay slot still runs before we get to target
+    pub fn branch(&mut self, target: u64) {
+        self.delay_slot_next = true;
+        self.branch_target = Some(target);
+    }
+
+    //a branch that falls through still owns the next instruction as its delay slot
+    pub fn branch_not_taken(&mut self) {
+        self.delay_slot_next = true;
+    }
+
+    //an untaken branch likely skips its delay slot entirely
+    pub fn nullify_delay_slot(&mut self) {
+        self.next_pc = self.next_pc.wrapping_add(4);
+    }
+
+    //retire the instruction at PC and move on to the next one
+    //NOTE: a branch sitting in a delay slot latches its own target here, so the instruction at
+    //the first branch's target runs (as the second branch's delay slot) before we get to the second target
+    pub fn advance_pc(&mut self) {
+        self.rf.PC = self.next_pc;
+        self.in_delay_slot = std::mem::take(&mut self.delay_slot_next);
+        self.next_pc = match self.branch_target.take() {
+            Some(target) => target,
+            None => self.rf.PC.wrapping_add(4),
+        };
+    }
+
+    //the one path every exception goes through on its way into the kernel
+    pub fn exception(&mut self, exc: Exception) {
+        debug!("taking exception {:?} at {:#x}", exc, self.rf.PC);
+
+        let (epc, bd) = self.exception_return_address();
+
+        //a reset doesnt touch Cause or EPC, it goes through ERL/ErrorEPC and always
+        //lands in the boot rom
+        if exc == Exception::ColdReset {
+            self.cop0.ErrorEPC = epc;
+            let status = &mut self.cop0.Status;
+            status.set_ERL(true);
+            status.set_BEV(true);
+            status.set_TS(false);
+            status.set_SR(false);
+            status.set_RP(false);
+            self.cop0.Random = 31;
+            self.cop0.Wired = 0;
+            self.set_pc(0xFFFF_FFFF_BFC0_0000);
+            return;
+        }
+
+        //refills only get their own vectors if we werent already handling an exception
+        let already_in_exception = self.cop0.Status.EXL();
+        //and which refill vector depends on the mode we faulted in, not the kernel mode EXL puts us in
+        let faulted_in_64bit = self.is_64bit_addressing();
+
+        //a nested exception keeps the original EPC and BD
+        if !already_in_exception {
+            self.cop0.EPC = epc;
+            self.cop0.Cause.set_BD(bd);
+        }
+        self.cop0.Cause.set_ExcCode(exc.code());
+        if let Exception::CoprocessorUnusable { cop } = exc {
+            self.cop0.Cause.set_CE(cop);
+        }
+        if let Some(vaddr) = exc.bad_vaddr() {
+            self.cop0.BadVAddr = vaddr;
+        }
+        //tlb exceptions also point Context/XContext at the PTE for the page and load its VPN2 into
+        //EntryHi, so that a refill handler can just TLBWR
+        if matches!(
+            exc,
+            Exception::TlbModification { .. }
+                | Exception::TlbLoad { .. }
+                | Exception::TlbStore { .. }
+        ) {
+            let vaddr = exc.bad_vaddr().unwrap();
+            self.cop0.Context =
+                (self.cop0.Context & 0xFFFF_FFFF_FF80_0000) | (((vaddr >> 13) & 0x7_FFFF) << 4);
+            self.cop0
+                .XContext
+                .set_BadVPN2(((vaddr >> 13) & 0x7FF_FFFF) as u32);
+            self.cop0.XContext.set_R((vaddr >> 62) as u8);
+            self.cop0.EntryHi = (vaddr & 0xC000_00FF_FFFF_E000) | (self.cop0.EntryHi & 0xFF);
+        }
+        self.cop0.Status.set_EXL(true);
+
+        //BEV moves the vectors out of RAM and into the boot rom
+        let base: u64 = if self.cop0.Status.BEV() {
+            0xFFFF_FFFF_BFC0_0200
+        } else {
+            0xFFFF_FFFF_8000_0000
+        };
+        let offset = match exc {
+            Exception::TlbLoad { refill: true, .. } | Exception::TlbStore { refill: true, .. }
+                if !already_in_exception =>
+            {
+                if faulted_in_64bit {
+                    0x080
+                } else {
+                    0x000
+                }
+            }
+            _ => 0x180,
+        };
+        self.set_pc(base + offset);
+    }
+
+    //charge the cpu for the pcycles the last op took
+    //Count runs at half the pclock and raises the timer interrupt (IP7) when it reaches Compare
+    pub fn tick(&mut self, cycles: usize) {
+        //Random drops by one every instruction, wrapping from Wired back up to 31
+        self.cop0.Random = if self.cop0.Random <= self.cop0.Wired {
+            31
+        } else {
+            self.cop0.Random - 1
+        };
+
+        let total = self.count_remainder + cycles as u64;
+        self.count_remainder = total % 2;
+        let ticks = (total / 2) as u32;
+        if ticks == 0 {
+            return;
+        }
+
+        let old = self.cop0.Count;
+        self.cop0.Count = old.wrapping_add(ticks);
+
+        //an op can take long enough for Count to move more than 1, so check if we passed Compare
+        //anywhere in (old, new] rather than just landing on it
+        if self.cop0.Compare.wrapping_sub(old).wrapping_sub(1) < ticks {
+            self.set_interrupt_pending(7, true);
+        }
+    }
+
+    //writing Compare is how the timer interrupt gets acknowledged
+    pub fn write_compare(&mut self, val: u32) {
+        self.cop0.Compare = val;
+        self.set_interrupt_pending(7, false);
+    }
+
+    //lets external devices drive the cpus interrupt pins. lines 2-6 map to Cause.IP2-IP6
+    //(the RCP sits on IP2), IP0/1 are software interrupts and IP7 is the timer
+    pub fn set_interrupt_line(&mut self, line: u8, asserted: bool) {
+        assert!(
+            (2..=6).contains(&line),
+            "interrupt line {} is not an external line",
+            line
+        );
+        self.set_interrupt_pending(line, asserted);
+    }
+
+    fn set_interrupt_pending(&mut self, bit: u8, pending: bool) {
+        let ip = self.cop0.Cause.IP();
+        let ip = if pending {
+            ip | (1 << bit)
+        } else {
+            ip & !(1 << bit)
+        };
+        self.cop0.Cause.set_IP(ip);
+    }
+
+    //an interrupt gets taken if its pending in Cause, unmasked in Status.IM, and interrupts are
+    //enabled and we arent already in an exception or error handler
+    pub fn interrupt_pending(&self) -> bool {
+        let status = self.cop0.Status;
+        status.IE() && !status.EXL() && !status.ERL() && (self.cop0.Cause.IP() & status.IM()) != 0
+    }
+
+    //0 = kernel, 1 = supervisor, 2 = user
+    //EXL and ERL force kernel mode no matter what KSU says
+    pub fn privilege_level(&self) -> u8 {
+        let status = self.cop0.Status;
+        if status.EXL() || status.ERL() {
+            0
+        } else {
+            status.KSU()
+        }
+    }
+
+    //Status.CU gates every coprocessor. cop0 is always usable from kernel mode
+    pub fn coprocessor_usable(&self, cop: u8) -> bool {
+        (cop == 0 && self.privilege_level() == 0) || self.cop0.Status.CU() & (1 << cop) != 0
+    }
+
+    //whether the current privilege level is using 64 bit addressing (KX/SX/UX)
+    pub fn is_64bit_addressing(&self) -> bool {
+        let status = self.cop0.Status;
+        match self.privilege_level() {
+            0 => status.KX(),
+            1 => status.SX(),
+            _ => status.UX(),
+        }
+    }
+
+    //where an exception taken at the current instruction should return to, and the value for Cause.BD
+    //exceptions in a delay slot return to the branch so that it gets re-executed
+    pub fn exception_return_address(&self) -> (u64, bool) {
+        if self.in_delay_slot {
+            (self.rf.PC.wrapping_sub(4), true)
+        } else {
+            (self.rf.PC, false)
+        }
+    }
+}
+
+//everything that can make the cpu take an exception
+//the vaddr carried by the memory ones ends up in BadVAddr
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exception {
+    Interrupt,
+    TlbModification { vaddr: u64 },
+    //instruction fetches count as loads
+    TlbLoad { vaddr: u64, refill: bool },
+    TlbStore { vaddr: u64, refill: bool },
+    AddressErrorLoad { vaddr: u64 },
+    AddressErrorStore { vaddr: u64 },
+    BusErrorInstruction,
+    BusErrorData,
+    Syscall,
+    Breakpoint,
+    ReservedInstruction,
+    CoprocessorUnusable { cop: u8 },
+    IntegerOverflow,
+    Trap,
+    FloatingPoint,
+    Watch,
+    ColdReset,
+}
+
+impl Exception {
+    //the value that goes into Cause.ExcCode for this exception
+    pub fn code(&self) -> u8 {
+        match self {
+            Exception::Interrupt => 0,
+            Exception::TlbModification { .. } => 1,
+            Exception::TlbLoad { .. } => 2,
+            Exception::TlbStore { .. } => 3,
+            Exception::AddressErrorLoad { .. } => 4,
+            Exception::AddressErrorStore { .. } => 5,
+            Exception::BusErrorInstruction => 6,
+            Exception::BusErrorData => 7,
+            Exception::Syscall => 8,
+            Exception::Breakpoint => 9,
+            Exception::ReservedInstruction => 10,
+            Exception::CoprocessorUnusable { .. } => 11,
+            Exception::IntegerOverflow => 12,
+            Exception::Trap => 13,
+            Exception::FloatingPoint => 15,
+            Exception::Watch => 23,
+            Exception::ColdReset => unreachable!("resets dont have an ExcCode"),
+        }
+    }
+
+    pub fn bad_vaddr(&self) -> Option<u64> {
+        match self {
+            Exception::TlbModification { vaddr }
+            | Exception::TlbLoad { vaddr, .. }
+            | Exception::TlbStore { vaddr, .. }
+            | Exception::AddressErrorLoad { vaddr }
+            | Exception::AddressErrorStore { vaddr } => Some(*vaddr),
+            _ => None,
         }
     }
 }
@@ -113,7 +585,7 @@ impl IndexMut<GPR> for Rf {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum GPR {
     zero,
     at,
@@ -259,7 +731,9 @@ impl From<u8> for GPR {
 31 — Reserved for future use
 */
 
-enum cop0reg {
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum cop0reg {
     Index,    //32 bit
     Random,   //32 bit
     EntryLo0, //64 bit (32 bit access sign extends)
@@ -268,6 +742,7 @@ enum cop0reg {
     PageMask, //64 bit (32 bit access sign extends)
     Wired,    //32 bit
     //7 — Reserved for future use
+    Reserved7,
     BadVAddr, //64 (32 ?)
     Count,    //32 bit
     EntryHi,  //64 bit (32 bit access sign extends)
@@ -282,12 +757,57 @@ enum cop0reg {
     WatchHi,  //32
     XContext, //64 NOTE: bitfield
     //21–25 — Reserved for future use
-    Parity, //32: bitfield
-    Cache,  //32
-    TagLo,  //32 bitfield
-    TagHi,  //32 bitfield
+    Reserved21,
+    Reserved22,
+    Reserved23,
+    Reserved24,
+    Reserved25,
+    Parity,   //32: bitfield
+    Cache,    //32
+    TagLo,    //32 bitfield
+    TagHi,    //32 bitfield
     ErrorEPC, //64 (32?)
-            //31 — Reserved for future use
+    //31 — Reserved for future use
+    Reserved31,
+}
+impl From<u8> for cop0reg {
+    fn from(val: u8) -> Self {
+        return match val {
+            0 => cop0reg::Index,
+            1 => cop0reg::Random,
+            2 => cop0reg::EntryLo0,
+            3 => cop0reg::EntryLo1,
+            4 => cop0reg::Context,
+            5 => cop0reg::PageMask,
+            6 => cop0reg::Wired,
+            7 => cop0reg::Reserved7,
+            8 => cop0reg::BadVAddr,
+            9 => cop0reg::Count,
+            10 => cop0reg::EntryHi,
+            11 => cop0reg::Compare,
+            12 => cop0reg::Status,
+            13 => cop0reg::Cause,
+            14 => cop0reg::EPC,
+            15 => cop0reg::PRId,
+            16 => cop0reg::Config,
+            17 => cop0reg::LLAddr,
+            18 => cop0reg::WatchLo,
+            19 => cop0reg::WatchHi,
+            20 => cop0reg::XContext,
+            21 => cop0reg::Reserved21,
+            22 => cop0reg::Reserved22,
+            23 => cop0reg::Reserved23,
+            24 => cop0reg::Reserved24,
+            25 => cop0reg::Reserved25,
+            26 => cop0reg::Parity,
+            27 => cop0reg::Cache,
+            28 => cop0reg::TagLo,
+            29 => cop0reg::TagHi,
+            30 => cop0reg::ErrorEPC,
+            31 => cop0reg::Reserved31,
+            _ => panic!("bad value"),
+        };
+    }
 }
 
 //make this indexable by an enum of all the registers it contains. impl Index and IndexMut traits
@@ -322,6 +842,9 @@ pub struct cop0 {
     //this is just always 0??
     pub TagHi: u32,    //32 bitfield
     pub ErrorEPC: u64, //64 (32?)*/
+
+    //every cop0 write also lands here, and its what the reserved registers read back as
+    pub latch: u64,
 }
 
 //status reg
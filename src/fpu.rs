@@ -0,0 +1,525 @@
+//arithmetic for cop1
+//the host only rounds to nearest, so every op works out which side of the rounded result the exact
+//result fell on and steps one ulp when FCR31.RM asks for a directed rounding
+//every op also hands back the FCR31 cause bits it raised, its up to the caller to trap or not
+
+use crate::cpu::FP_control_reg;
+use crate::ir::FpuOps;
+use std::cmp::Ordering;
+use std::ops::{Add, Mul, Neg, Sub};
+
+//FCR31 cause bits, Enables and Flags use the same layout minus E
+pub const INEXACT: u8 = 1 << 0;
+pub const UNDERFLOW: u8 = 1 << 1;
+pub const OVERFLOW: u8 = 1 << 2;
+pub const DIV_BY_ZERO: u8 = 1 << 3;
+pub const INVALID: u8 = 1 << 4;
+//E, the vr4300 punts anything it cant do in hardware to software with this. it cant be masked
+pub const UNIMPLEMENTED: u8 = 1 << 5;
+
+//FCR31.RM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoundingMode {
+    Nearest,
+    Zero,
+    PlusInf,
+    MinusInf,
+}
+
+impl From<u8> for RoundingMode {
+    fn from(rm: u8) -> Self {
+        match rm & 0b11 {
+            0 => RoundingMode::Nearest,
+            1 => RoundingMode::Zero,
+            2 => RoundingMode::PlusInf,
+            _ => RoundingMode::MinusInf,
+        }
+    }
+}
+
+pub trait FpuFloat:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const MIN_POSITIVE: Self;
+    //what an invalid operation hands back, the vr4300 uses the old mips quiet nan encoding
+    const DEFAULT_NAN: Self;
+
+    fn is_finite(self) -> bool;
+    fn is_nan(self) -> bool;
+    //old mips nans are signaling with the top mantissa bit set, the opposite of everyone else
+    fn is_signaling(self) -> bool;
+    fn is_subnormal(self) -> bool;
+    fn is_sign_negative(self) -> bool;
+    fn abs(self) -> Self;
+    fn next_up(self) -> Self;
+    fn next_down(self) -> Self;
+    fn to_f64(self) -> f64;
+    //to an integral value in the given rounding mode
+    fn round_int(self, rm: RoundingMode) -> Self;
+
+    //rounded to nearest, plus which way the exact result lies from it
+    fn mul_exact(a: Self, b: Self) -> (Self, Ordering);
+    fn div_exact(a: Self, b: Self) -> (Self, Ordering);
+    fn sqrt_exact(a: Self) -> (Self, Ordering);
+    fn from_int_exact(v: i64) -> (Self, Ordering);
+}
+
+impl FpuFloat for f64 {
+    const ZERO: Self = 0.0;
+    const MIN_POSITIVE: Self = f64::MIN_POSITIVE;
+    const DEFAULT_NAN: Self = f64::from_bits(0x7FF7_FFFF_FFFF_FFFF);
+
+    fn is_finite(self) -> bool {
+        f64::is_finite(self)
+    }
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+    fn is_signaling(self) -> bool {
+        self.is_nan() && self.to_bits() & (1 << 51) != 0
+    }
+    fn is_subnormal(self) -> bool {
+        f64::is_subnormal(self)
+    }
+    fn is_sign_negative(self) -> bool {
+        f64::is_sign_negative(self)
+    }
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+    fn next_up(self) -> Self {
+        f64::next_up(self)
+    }
+    fn next_down(self) -> Self {
+        f64::next_down(self)
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
+    fn round_int(self, rm: RoundingMode) -> Self {
+        match rm {
+            RoundingMode::Nearest => self.round_ties_even(),
+            RoundingMode::Zero => self.trunc(),
+            RoundingMode::PlusInf => self.ceil(),
+            RoundingMode::MinusInf => self.floor(),
+        }
+    }
+
+    //fma gives the exact remainder of all of these
+    fn mul_exact(a: Self, b: Self) -> (Self, Ordering) {
+        let q = a * b;
+        (q, sign(a.mul_add(b, -q)))
+    }
+    fn div_exact(a: Self, b: Self) -> (Self, Ordering) {
+        let q = a / b;
+        let rem = sign((-q).mul_add(b, a));
+        (q, if b < 0.0 { rem.reverse() } else { rem })
+    }
+    fn sqrt_exact(a: Self) -> (Self, Ordering) {
+        let q = a.sqrt();
+        (q, sign((-q).mul_add(q, a)))
+    }
+    fn from_int_exact(v: i64) -> (Self, Ordering) {
+        let q = v as f64;
+        (q, (v as i128).cmp(&(q as i128)))
+    }
+}
+
+impl FpuFloat for f32 {
+    const ZERO: Self = 0.0;
+    const MIN_POSITIVE: Self = f32::MIN_POSITIVE;
+    const DEFAULT_NAN: Self = f32::from_bits(0x7FBF_FFFF);
+
+    fn is_finite(self) -> bool {
+        f32::is_finite(self)
+    }
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+    fn is_signaling(self) -> bool {
+        self.is_nan() && self.to_bits() & (1 << 22) != 0
+    }
+    fn is_subnormal(self) -> bool {
+        f32::is_subnormal(self)
+    }
+    fn is_sign_negative(self) -> bool {
+        f32::is_sign_negative(self)
+    }
+    fn abs(self) -> Self {
+        f32::abs(self)
+    }
+    fn next_up(self) -> Self {
+        f32::next_up(self)
+    }
+    fn next_down(self) -> Self {
+        f32::next_down(self)
+    }
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+    fn round_int(self, rm: RoundingMode) -> Self {
+        match rm {
+            RoundingMode::Nearest => self.round_ties_even(),
+            RoundingMode::Zero => self.trunc(),
+            RoundingMode::PlusInf => self.ceil(),
+            RoundingMode::MinusInf => self.floor(),
+        }
+    }
+
+    //singles have few enough mantissa bits that the exact answers fit in a double
+    fn mul_exact(a: Self, b: Self) -> (Self, Ordering) {
+        let exact = a as f64 * b as f64;
+        let q = exact as f32;
+        (q, sign(exact - q as f64))
+    }
+    fn div_exact(a: Self, b: Self) -> (Self, Ordering) {
+        let q = a / b;
+        let rem = sign((-(q as f64)).mul_add(b as f64, a as f64));
+        (q, if b < 0.0 { rem.reverse() } else { rem })
+    }
+    fn sqrt_exact(a: Self) -> (Self, Ordering) {
+        let q = a.sqrt();
+        (q, sign((-(q as f64)).mul_add(q as f64, a as f64)))
+    }
+    fn from_int_exact(v: i64) -> (Self, Ordering) {
+        let q = v as f32;
+        (q, (v as i128).cmp(&(q as i128)))
+    }
+}
+
+fn sign<F: FpuFloat>(v: F) -> Ordering {
+    v.partial_cmp(&F::ZERO).unwrap_or(Ordering::Equal)
+}
+
+//moves a round to nearest result to where the rounding mode wants it
+//residual is the sign of (exact - q)
+fn round<F: FpuFloat>(q: F, residual: Ordering, rm: RoundingMode) -> F {
+    match (rm, residual) {
+        (_, Ordering::Equal) | (RoundingMode::Nearest, _) => q,
+        (RoundingMode::PlusInf, Ordering::Greater) => q.next_up(),
+        (RoundingMode::MinusInf, Ordering::Less) => q.next_down(),
+        (RoundingMode::Zero, Ordering::Greater) if q < F::ZERO => q.next_up(),
+        (RoundingMode::Zero, Ordering::Less) if q > F::ZERO => q.next_down(),
+        _ => q,
+    }
+}
+
+//NaNs and denormals never make it into the vr4300's datapath
+//signaling NaNs and denormals are unimplemented, quiet NaNs are invalid
+fn check_inputs<F: FpuFloat>(operands: &[F]) -> u8 {
+    let cause = operands.iter().fold(0, |cause, v| {
+        cause
+            | if v.is_signaling() || v.is_subnormal() {
+                UNIMPLEMENTED
+            } else if v.is_nan() {
+                INVALID
+            } else {
+                0
+            }
+    });
+    if cause & UNIMPLEMENTED != 0 {
+        UNIMPLEMENTED
+    } else {
+        cause
+    }
+}
+
+//rounds q and works out the cause bits of the result
+//finite operands that rounded to infinity overflowed, the exact result sits on the near side of it
+fn settle<F: FpuFloat>(
+    q: F,
+    finite_operands: bool,
+    residual: Ordering,
+    csr: FP_control_reg,
+) -> (F, u8) {
+    let rm = RoundingMode::from(csr.RM());
+    if q.is_nan() {
+        return (F::DEFAULT_NAN, INVALID);
+    }
+    if !q.is_finite() && !finite_operands {
+        return (q, 0);
+    }
+
+    //finite operands that rounded to nearest infinity overflowed whatever the rounding mode,
+    //the directed modes only decide whether the result is that infinity or MAX
+    let overflowed = !q.is_finite();
+    let residual = if q.is_finite() {
+        residual
+    } else if q > F::ZERO {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    };
+    let mut cause = if residual == Ordering::Equal {
+        0
+    } else {
+        INEXACT
+    };
+    let mut result = round(q, residual, rm);
+
+    //rounding MAX up overflows too
+    if overflowed || !result.is_finite() {
+        cause |= OVERFLOW | INEXACT;
+    }
+
+    //the vr4300 cant produce denormals. with FS set and neither U nor I trapping they get flushed,
+    //otherwise its an unimplemented op
+    if result.is_subnormal() || (result == F::ZERO && cause & INEXACT != 0) {
+        if !csr.FS() || csr.Enables() & (UNDERFLOW | INEXACT) != 0 {
+            return (result, UNIMPLEMENTED);
+        }
+        cause |= UNDERFLOW | INEXACT;
+        let negative = result.is_sign_negative();
+        result = match rm {
+            RoundingMode::PlusInf if !negative => F::MIN_POSITIVE,
+            RoundingMode::MinusInf if negative => -F::MIN_POSITIVE,
+            _ if negative => -F::ZERO,
+            _ => F::ZERO,
+        };
+    }
+
+    (result, cause)
+}
+
+pub fn add<F: FpuFloat>(a: F, b: F, csr: FP_control_reg) -> (F, u8) {
+    let cause = check_inputs(&[a, b]);
+    if cause != 0 {
+        return (F::DEFAULT_NAN, cause);
+    }
+
+    let q = a + b;
+    let finite = a.is_finite() && b.is_finite();
+
+    //an exact zero from opposite signs is +0, except when rounding toward -inf
+    if q == F::ZERO
+        && RoundingMode::from(csr.RM()) == RoundingMode::MinusInf
+        && (a.is_sign_negative() || b.is_sign_negative())
+    {
+        return (-F::ZERO, 0);
+    }
+
+    //2sum, the error term is exact as long as nothing overflowed
+    let residual = if finite && q.is_finite() {
+        let bb = q - a;
+        sign((a - (q - bb)) + (b - bb))
+    } else {
+        Ordering::Equal
+    };
+    settle(q, finite, residual, csr)
+}
+
+pub fn sub<F: FpuFloat>(a: F, b: F, csr: FP_control_reg) -> (F, u8) {
+    add(a, -b, csr)
+}
+
+pub fn mul<F: FpuFloat>(a: F, b: F, csr: FP_control_reg) -> (F, u8) {
+    let cause = check_inputs(&[a, b]);
+    if cause != 0 {
+        return (F::DEFAULT_NAN, cause);
+    }
+    let (q, residual) = F::mul_exact(a, b);
+    settle(q, a.is_finite() && b.is_finite(), residual, csr)
+}
+
+pub fn div<F: FpuFloat>(a: F, b: F, csr: FP_control_reg) -> (F, u8) {
+    let cause = check_inputs(&[a, b]);
+    if cause != 0 {
+        return (F::DEFAULT_NAN, cause);
+    }
+    let (q, residual) = F::div_exact(a, b);
+    //x/0 is an exact infinity, not an overflow. 0/0 falls through to invalid
+    if b == F::ZERO {
+        if a.is_finite() && a != F::ZERO {
+            return (q, DIV_BY_ZERO);
+        }
+        return settle(q, false, Ordering::Equal, csr);
+    }
+    settle(q, a.is_finite() && b.is_finite(), residual, csr)
+}
+
+pub fn sqrt<F: FpuFloat>(a: F, csr: FP_control_reg) -> (F, u8) {
+    let cause = check_inputs(&[a]);
+    if cause != 0 {
+        return (F::DEFAULT_NAN, cause);
+    }
+    let (q, residual) = F::sqrt_exact(a);
+    settle(q, a.is_finite(), residual, csr)
+}
+
+//the two operand ops all in one place so S and D can share them
+pub fn arith<F: FpuFloat>(op: &FpuOps, a: F, b: F, csr: FP_control_reg) -> (F, u8) {
+    match op {
+        FpuOps::ADD => add(a, b, csr),
+        FpuOps::SUB => sub(a, b, csr),
+        FpuOps::MUL => mul(a, b, csr),
+        FpuOps::DIV => div(a, b, csr),
+        FpuOps::SQRT => sqrt(a, csr),
+        //ABS and NEG still go through the datapath, so they check their input like everything else
+        FpuOps::ABS | FpuOps::NEG => {
+            let cause = check_inputs(&[a]);
+            if cause != 0 {
+                return (F::DEFAULT_NAN, cause);
+            }
+            (
+                if matches!(op, FpuOps::ABS) {
+                    a.abs()
+                } else {
+                    -a
+                },
+                0,
+            )
+        }
+        _ => unreachable!("{:?} is not an arithmetic fpu op", op),
+    }
+}
+
+//C.cond
+//the low three bits of cond pick which of unordered, equal and less than make the compare true
+//the 4th bit makes unordered operands invalid, signaling NaNs always are
+pub fn compare<F: FpuFloat>(a: F, b: F, cond: u8) -> (bool, u8) {
+    match a.partial_cmp(&b) {
+        None => {
+            let cause = if cond & 0b1000 != 0 || a.is_signaling() || b.is_signaling() {
+                INVALID
+            } else {
+                0
+            };
+            (cond & 0b001 != 0, cause)
+        }
+        Some(Ordering::Equal) => (cond & 0b010 != 0, 0),
+        Some(Ordering::Less) => (cond & 0b100 != 0, 0),
+        Some(Ordering::Greater) => (false, 0),
+    }
+}
+
+//CVT.S.D
+pub fn to_single(a: f64, csr: FP_control_reg) -> (f32, u8) {
+    let cause = check_inputs(&[a]);
+    if cause != 0 {
+        return (f32::DEFAULT_NAN, cause);
+    }
+    let q = a as f32;
+    settle(q, a.is_finite(), sign(a - q as f64), csr)
+}
+
+//CVT.D.S, always exact
+pub fn to_double(a: f32) -> (f64, u8) {
+    let cause = check_inputs(&[a]);
+    if cause != 0 {
+        return (f64::DEFAULT_NAN, cause);
+    }
+    (a as f64, 0)
+}
+
+//CVT.S.W/CVT.S.L/CVT.D.W/CVT.D.L
+//the vr4300 only converts longs that fit in 55 bits, bigger ones are unimplemented
+pub fn from_int<F: FpuFloat>(v: i64, csr: FP_control_reg) -> (F, u8) {
+    if !(-(1 << 55)..(1 << 55)).contains(&v) {
+        return (F::DEFAULT_NAN, UNIMPLEMENTED);
+    }
+    let (q, residual) = F::from_int_exact(v);
+    settle(q, true, residual, csr)
+}
+
+//CVT/ROUND/TRUNC/CEIL/FLOOR to W or L
+//instead of the IEEE invalid operation, anything that doesnt fit is unimplemented. for L that means
+//anything outside +-2^53, not the full 64 bit range
+pub fn to_int<F: FpuFloat>(a: F, rm: RoundingMode, long: bool) -> (i64, u8) {
+    if a.is_nan() || !a.is_finite() || a.is_subnormal() {
+        return (0, UNIMPLEMENTED);
+    }
+    let r = a.round_int(rm).to_f64();
+    let in_range = if long {
+        r > -(2f64.powi(53)) && r < 2f64.powi(53)
+    } else {
+        (-(2f64.powi(31))..2f64.powi(31)).contains(&r)
+    };
+    if !in_range {
+        return (0, UNIMPLEMENTED);
+    }
+    let cause = if r == a.to_f64() { 0 } else { INEXACT };
+    (r as i64, cause)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn csr(rm: RoundingMode, fs: bool) -> FP_control_reg {
+        let rm = match rm {
+            RoundingMode::Nearest => 0,
+            RoundingMode::Zero => 1,
+            RoundingMode::PlusInf => 2,
+            RoundingMode::MinusInf => 3,
+        };
+        FP_control_reg(0).with_RM(rm).with_FS(fs)
+    }
+
+    //every mode flags overflow, the mode only picks between infinity and MAX
+    fn check_overflow(rm: RoundingMode, positive: f64, negative: f64) {
+        let (r, cause) = mul(f64::MAX, 2.0, csr(rm, false));
+        assert_eq!(r, positive);
+        assert_eq!(cause, OVERFLOW | INEXACT);
+        let (r, cause) = mul(-f64::MAX, 2.0, csr(rm, false));
+        assert_eq!(r, negative);
+        assert_eq!(cause, OVERFLOW | INEXACT);
+
+        //CVT.S.D lands on the single precision MAX instead
+        let single = |v: f64| {
+            if v.is_infinite() {
+                v as f32
+            } else {
+                f32::MAX.copysign(v as f32)
+            }
+        };
+        let (r, cause) = to_single(f64::MAX, csr(rm, false));
+        assert_eq!(r, single(positive));
+        assert_eq!(cause, OVERFLOW | INEXACT);
+        let (r, cause) = to_single(-f64::MAX, csr(rm, false));
+        assert_eq!(r, single(negative));
+        assert_eq!(cause, OVERFLOW | INEXACT);
+    }
+
+    #[test]
+    fn overflow_nearest() {
+        check_overflow(RoundingMode::Nearest, f64::INFINITY, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn overflow_zero() {
+        check_overflow(RoundingMode::Zero, f64::MAX, -f64::MAX);
+    }
+
+    #[test]
+    fn overflow_plus_inf() {
+        check_overflow(RoundingMode::PlusInf, f64::INFINITY, -f64::MAX);
+    }
+
+    #[test]
+    fn overflow_minus_inf() {
+        check_overflow(RoundingMode::MinusInf, f64::MAX, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn underflow_without_fs_is_unimplemented() {
+        let (_, cause) = mul(f64::MIN_POSITIVE, 0.5, csr(RoundingMode::Nearest, false));
+        assert_eq!(cause, UNIMPLEMENTED);
+    }
+
+    #[test]
+    fn underflow_with_fs_flushes() {
+        let (r, cause) = mul(f64::MIN_POSITIVE, 0.5, csr(RoundingMode::Nearest, true));
+        assert_eq!(r, 0.0);
+        assert_eq!(cause, UNDERFLOW | INEXACT);
+
+        let (r, cause) = mul(-f64::MIN_POSITIVE, 0.5, csr(RoundingMode::MinusInf, true));
+        assert_eq!(r, -f64::MIN_POSITIVE);
+        assert_eq!(cause, UNDERFLOW | INEXACT);
+    }
+}
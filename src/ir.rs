@@ -3,6 +3,7 @@
 //then we can turn the IrOp into a host opcode in a platform agnostic manner
 //this host opcode is what will then be added into an executablebuffer considerd a compiled block
 
+use crate::cpu::cop0reg;
 use crate::cpu::GPR;
 use colored::Colorize;
 
@@ -27,13 +28,16 @@ use colored::Colorize;
 pub enum Op {
     Load {
         width: usize,
+        //sign or zero extend the loaded value into the 64 bit dest
+        signed: bool,
         //this needs to be an enum because of our LWC and SWC ops
         dest: GPRorCoPGPR,
         base: Option<GPR>,
         offset: Option<u16>,
         condtional: bool,
         aligned: bool,
-        imm_src: Option<usize>,
+        //only meaningful for unaligned accesses, picks between the L and R variants
+        left: bool,
     },
     Store {
         width: usize,
@@ -43,6 +47,8 @@ pub enum Op {
         offset: Option<u16>,
         conditional: bool,
         aligned: bool,
+        //only meaningful for unaligned accesses, picks between the L and R variants
+        left: bool,
         imm_src: Option<usize>,
     },
 
@@ -67,33 +73,180 @@ pub enum Op {
         register: Option<GPRorCoPGPR>,
         //fuck you mips. need this field so we know if we are dealing with delay slots or not
         likely: bool,
-        link: bool,
+        //the reg that gets the return address, ra for everything except JALR
+        link: Option<GPR>,
     },
 
     Move {
+        //32 for the plain moves, 64 for the doubleword ones
+        width: usize,
         src: GPRorCoPGPR,
         dest: GPRorCoPGPR,
     },
 
+    //cop1 arithmetic. the registers are fpr numbers
+    FpuOp {
+        op_type: FpuOps,
+        fmt: FpuFormat,
+        dst: u8,
+        src_1: u8,
+        src_2: u8,
+    },
+
     System {
         opcode: SystemOp,
     },
 
-    //this WILL fire a
+    //a coprocessor slot the vr4300 doesnt define, including all of cop2 and cop3 but the cop2 latch
+    //the CU check still happens first, only then is it a reserved instruction
+    ReservedCop {
+        cop: u8,
+    },
+
+    //an encoding the vr4300 doesnt define, executing it raises a reserved instruction exception
     MalformedOp,
 }
 
+impl Op {
+    //which coprocessor this op needs Status.CU for, if any
+    pub fn coprocessor(&self) -> Option<u8> {
+        let reg_cop = |r: &GPRorCoPGPR| match r {
+            GPRorCoPGPR::gpr(_) => None,
+            GPRorCoPGPR::cop0(_) => Some(0),
+            GPRorCoPGPR::cop1(_) | GPRorCoPGPR::cop1_control(_) => Some(1),
+            GPRorCoPGPR::cop2_latch => Some(2),
+        };
+        match self {
+            Op::Load { dest, .. } => reg_cop(dest),
+            Op::Store { src, .. } => reg_cop(src),
+            Op::Move { src, dest, .. } => reg_cop(src).or(reg_cop(dest)),
+            Op::FpuOp { .. } => Some(1),
+            Op::ControlFlow {
+                conditional:
+                    ControlConditionalType::CopZTrue { cop } | ControlConditionalType::CopZFalse { cop },
+                ..
+            } => Some(*cop as u8),
+            //everything but the exception ops lives in cop0
+            Op::System {
+                opcode: SystemOp::Cache { .. } | SystemOp::Tlb { .. } | SystemOp::Eret,
+            } => Some(0),
+            Op::ReservedCop { cop } => Some(*cop),
+            _ => None,
+        }
+    }
+
+    //the doubleword ops, which are reserved in 32 bit user and supervisor mode
+    pub fn is_64bit(&self) -> bool {
+        match self {
+            Op::AluOp { op_type, .. } => matches!(
+                op_type,
+                AluOps::DADD
+                    | AluOps::DADDI
+                    | AluOps::DADDIU
+                    | AluOps::DADDU
+                    | AluOps::DSUB
+                    | AluOps::DSUBU
+                    | AluOps::DSLL
+                    | AluOps::DSLL32
+                    | AluOps::DSLLV
+                    | AluOps::DSRA
+                    | AluOps::DSRA32
+                    | AluOps::DSRAV
+                    | AluOps::DSRL
+                    | AluOps::DSRL32
+                    | AluOps::DSRLV
+                    | AluOps::DMULT
+                    | AluOps::DMULTU
+                    | AluOps::DDIV
+                    | AluOps::DDIVU
+            ),
+            //LD/LDL/LDR/LLD/SD/SDL/SDR/SCD, LDC1/SDC1 are fine
+            Op::Load {
+                width: 64,
+                dest: GPRorCoPGPR::gpr(_),
+                ..
+            }
+            | Op::Store {
+                width: 64,
+                src: GPRorCoPGPR::gpr(_),
+                ..
+            } => true,
+            //LWU
+            Op::Load {
+                width: 32,
+                signed: false,
+                aligned: true,
+                dest: GPRorCoPGPR::gpr(_),
+                ..
+            } => true,
+            //DMFC0/DMTC0/DMFC1/DMTC1. the cop2 latch moves are all decoded as 64 bit but arent really
+            Op::Move {
+                width: 64,
+                src,
+                dest,
+            } => {
+                !matches!(src, GPRorCoPGPR::cop2_latch) && !matches!(dest, GPRorCoPGPR::cop2_latch)
+            }
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SystemOp {
-    Cache,
+    Cache {
+        op: CacheOp,
+        base: GPR,
+        offset: u16,
+    },
     Syscall,
-    Break,
+    //code is the 20 bit field software can stash in the instruction
+    Break {
+        code: u32,
+    },
     Sync,
-    Trap { condition: ControlConditionalType },
-    Tlb,
+    Trap {
+        condition: ControlConditionalType,
+        register: GPR,
+    },
+    Tlb {
+        op: TlbOp,
+    },
     Eret,
 }
 
+//CACHE ops, already split by which cache they hit
+#[derive(Debug, Clone)]
+pub enum CacheOp {
+    //the index ops pick a line straight from the low bits of the virtual address
+    //on the data cache a dirty line is written back before being invalidated
+    IndexInvalidate { data: bool },
+    IndexLoadTag { data: bool },
+    IndexStoreTag { data: bool },
+    //data cache only, claims a line for an address without filling it
+    CreateDirtyExclusive,
+    //the hit ops only do anything if the address is actually in the cache
+    HitInvalidate { data: bool },
+    HitWriteBackInvalidate,
+    HitWriteBack { data: bool },
+    //instruction cache only, loads the line from memory unconditionally
+    Fill,
+    //secondary cache ops and the holes in the table. the vr4300 has no secondary cache so these do nothing
+    Undefined,
+}
+
+#[derive(Debug, Clone)]
+pub enum TlbOp {
+    //TLBR
+    Read,
+    //TLBWI
+    WriteIndexed,
+    //TLBWR
+    WriteRandom,
+    //TLBP
+    Probe,
+}
+
 #[derive(Debug, Clone)]
 pub enum AluOps {
     //immeadiate ops
@@ -156,6 +309,75 @@ pub enum AluOps {
     MTHI,
     MTLO,
 }
+impl AluOps {
+    //how many pcycles this op takes on the vr4300, everything not listed here is single cycle
+    //these are the mult/div latencies from the manual, for the timing model to charge
+    //(vr4300 user's manual, cpu instruction set details). the unsigned divides skip the sign
+    //fixup and come in a cycle under the signed ones
+    pub fn cycles(&self) -> usize {
+        match self {
+            AluOps::MULT | AluOps::MULTU => 5,
+            AluOps::DMULT | AluOps::DMULTU => 8,
+            AluOps::DIV => 37,
+            AluOps::DIVU => 36,
+            AluOps::DDIV => 69,
+            AluOps::DDIVU => 68,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FpuOps {
+    ADD,
+    SUB,
+    MUL,
+    DIV,
+    SQRT,
+    ABS,
+    MOV,
+    NEG,
+    //C.cond, the low 4 bits of the function field
+    C { cond: u8 },
+    //conversions. CVT rounds by FCR31.RM, the others by their name
+    CVT { to: FpuFormat },
+    ROUND { to: FpuFormat },
+    TRUNC { to: FpuFormat },
+    CEIL { to: FpuFormat },
+    FLOOR { to: FpuFormat },
+}
+
+impl FpuOps {
+    //pcycles per the manual, single and double only differ for mul/div/sqrt
+    pub fn cycles(&self, fmt: &FpuFormat) -> usize {
+        let double = matches!(fmt, FpuFormat::D);
+        match self {
+            FpuOps::ADD | FpuOps::SUB => 3,
+            FpuOps::MUL if double => 8,
+            FpuOps::MUL => 5,
+            FpuOps::DIV | FpuOps::SQRT if double => 58,
+            FpuOps::DIV | FpuOps::SQRT => 29,
+            FpuOps::CVT { to: FpuFormat::D } if matches!(fmt, FpuFormat::S) => 1,
+            FpuOps::CVT { to: FpuFormat::S } if double => 2,
+            FpuOps::CVT { .. }
+            | FpuOps::ROUND { .. }
+            | FpuOps::TRUNC { .. }
+            | FpuOps::CEIL { .. }
+            | FpuOps::FLOOR { .. } => 5,
+            _ => 1,
+        }
+    }
+}
+
+//the fmt field of a cop1 op
+#[derive(Debug, Clone)]
+pub enum FpuFormat {
+    S,
+    D,
+    W,
+    L,
+}
+
 #[derive(Debug, Clone)]
 pub enum AluOpSrc {
     Imm(u16),
@@ -171,7 +393,13 @@ pub enum shamt {
 #[derive(Debug, Clone)]
 pub enum GPRorCoPGPR {
     gpr(GPR),
-    cop,
+    cop0(cop0reg),
+    //fpr number
+    cop1(u8),
+    //fcr number, only 0 and 31 exist
+    cop1_control(u8),
+    //cop2 has no registers, just the one latch every move goes through
+    cop2_latch,
 }
 
 #[derive(Debug, Clone)]
@@ -181,6 +409,17 @@ pub enum ControlConditionalType {
     CopZTrue { cop: usize },
     Eq { reg2: GPR },
     Ne { reg2: GPR },
+    //these are only used by the trap family
+    Ge { reg2: GPR },
+    GeU { reg2: GPR },
+    Lt { reg2: GPR },
+    LtU { reg2: GPR },
+    GeI { imm: u16 },
+    GeIU { imm: u16 },
+    LtI { imm: u16 },
+    LtIU { imm: u16 },
+    EqI { imm: u16 },
+    NeI { imm: u16 },
     GEZ,
     GTZ,
     LEZ,
@@ -189,13 +428,15 @@ pub enum ControlConditionalType {
 
 #[derive(Debug, Clone)]
 pub enum ControlDestType {
+    //J/JAL: only the low 28 bits, the rest come from the delay slot address
     Absolute { dest: usize },
+    //relative to the address of the delay slot
     Relative { offset: i64 },
     Register { r: GPR },
 }
 
 //this function takes a guest machine code basic block as parsed elsewhere and returns an ir basic block
-pub fn lift(block: Vec<(u64, u32)>) -> Vec<(Op, u64)> {
+pub fn lift(block: Vec<(u64, u32)>) -> Result<Vec<(Op, u64)>, LiftError> {
     let disas = mipsasm::Mipsasm::new();
 
     let mut ir_block: Vec<(Op, u64)> = Vec::new();
@@ -211,25 +452,50 @@ pub fn lift(block: Vec<(u64, u32)>) -> Vec<(Op, u64)> {
             )
             .blue()
         );
-        let ir_op = guest_to_ir(instr.1).unwrap();
+        let ir_op = guest_to_ir(instr.1).map_err(|e| LiftError::AtAddress {
+            addr: instr.0,
+            source: Box::new(e),
+        })?;
         log::info!("{}\n", format!("{:?}", ir_op).blue());
 
         ir_block.push((ir_op, instr.0));
     }
 
-    ir_block
+    Ok(ir_block)
 }
 
+//which decode table the lifter was in
+#[derive(Debug, Clone, Copy)]
+pub enum DecodeTable {
+    Main,
+    Special,
+    Regimm,
+    Cop0,
+    Cop1,
+    Cop2,
+}
+
+//guest encodings never fail to lift, undefined ones become MalformedOp or ReservedCop
+//these are only for bugs in the lifter itself
 #[derive(Debug, Clone)]
-pub enum LiftError {}
+pub enum LiftError {
+    //a table was indexed with a value its field cant hold
+    FieldOutOfRange {
+        instr: u32,
+        table: DecodeTable,
+        field: u32,
+    },
+    //the same thing with the guest address attached, from lift
+    AtAddress {
+        addr: u64,
+        source: Box<LiftError>,
+    },
+}
 
 //we always return opcodes in an Ok variant, regardless of if they are valid opcodes or not
 //catching invalid opcodes is a runtime issue
-//the Err vatiant here is used for language err handling (catching panics and such)
+//the Err variant is only for the lifter catching itself out, see LiftError
 pub fn guest_to_ir(instr: u32) -> Result<Op, LiftError> {
-    //DEBUG: need this to pretty print disasembly
-    let disas = mipsasm::Mipsasm::new();
-
     //top 5 bits are always the opcode, with the caveat that a full 0 opcode needs more decoding
     let opcode = (instr & 0xFC00_0000) >> 26;
 
@@ -243,6 +509,8 @@ pub fn guest_to_ir(instr: u32) -> Result<Op, LiftError> {
     let i_op_rs = ((instr & 0x03E0_0000) >> 21) as u8;
     let i_op_rt = ((instr & 0x001F_0000) >> 16) as u8;
     let i_op_imm = (instr & 0x0000_FFFF) as u16;
+    //branches use the immediate as a signed word offset from the delay slot
+    let branch_offset = (i_op_imm as i16 as i64) << 2;
 
     return match opcode {
         //SPECIAL decoding
@@ -284,12 +552,10 @@ pub fn guest_to_ir(instr: u32) -> Result<Op, LiftError> {
                 }),
 
                 //RESERVED INSTRUCTION EXCEPTION
-                0x05 => {
-                    return Ok(Op::MalformedOp);
-                }
+                0x05 => Ok(Op::MalformedOp),
                 //SRLV
                 0x06 => Ok(Op::AluOp {
-                    op_type: AluOps::SLLV,
+                    op_type: AluOps::SRLV,
                     dst: r_op_rd.into(),
                     src_1: Some(r_op_rt.into()),
                     src_2: AluOpSrc::Reg(r_op_rs.into()),
@@ -307,17 +573,514 @@ pub fn guest_to_ir(instr: u32) -> Result<Op, LiftError> {
                     destination: ControlDestType::Register { r: r_op_rs.into() },
                     register: None,
                     likely: false,
-                    link: false,
+                    link: None,
+                }),
+                //JALR
+                0x09 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::Unconditional,
+                    destination: ControlDestType::Register { r: r_op_rs.into() },
+                    register: None,
+                    likely: false,
+                    link: Some(r_op_rd.into()),
+                }),
+                //SYSCALL
+                0x0C => Ok(Op::System {
+                    opcode: SystemOp::Syscall,
+                }),
+                //BREAK
+                0x0D => Ok(Op::System {
+                    opcode: SystemOp::Break {
+                        code: (instr >> 6) & 0xF_FFFF,
+                    },
+                }),
+                //SYNC
+                0x0F => Ok(Op::System {
+                    opcode: SystemOp::Sync,
+                }),
+                //MFHI
+                //HI/LO moves have no real sources, the unit is implicit
+                0x10 => Ok(Op::AluOp {
+                    op_type: AluOps::MFHI,
+                    dst: r_op_rd.into(),
+                    src_1: None,
+                    src_2: AluOpSrc::Imm(0),
+                }),
+                //MTHI
+                0x11 => Ok(Op::AluOp {
+                    op_type: AluOps::MTHI,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Imm(0),
+                }),
+                //MFLO
+                0x12 => Ok(Op::AluOp {
+                    op_type: AluOps::MFLO,
+                    dst: r_op_rd.into(),
+                    src_1: None,
+                    src_2: AluOpSrc::Imm(0),
+                }),
+                //MTLO
+                0x13 => Ok(Op::AluOp {
+                    op_type: AluOps::MTLO,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Imm(0),
+                }),
+                //DSLLV
+                0x14 => Ok(Op::AluOp {
+                    op_type: AluOps::DSLLV,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Reg(r_op_rs.into()),
+                }),
+                //DSRLV
+                0x16 => Ok(Op::AluOp {
+                    op_type: AluOps::DSRLV,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Reg(r_op_rs.into()),
+                }),
+                //DSRAV
+                0x17 => Ok(Op::AluOp {
+                    op_type: AluOps::DSRAV,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Reg(r_op_rs.into()),
+                }),
+                //MULT
+                //mult/div results go to HI/LO, so there is no real dst
+                0x18 => Ok(Op::AluOp {
+                    op_type: AluOps::MULT,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //MULTU
+                0x19 => Ok(Op::AluOp {
+                    op_type: AluOps::MULTU,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DIV
+                0x1A => Ok(Op::AluOp {
+                    op_type: AluOps::DIV,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DIVU
+                0x1B => Ok(Op::AluOp {
+                    op_type: AluOps::DIVU,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DMULT
+                0x1C => Ok(Op::AluOp {
+                    op_type: AluOps::DMULT,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DMULTU
+                0x1D => Ok(Op::AluOp {
+                    op_type: AluOps::DMULTU,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DDIV
+                0x1E => Ok(Op::AluOp {
+                    op_type: AluOps::DDIV,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DDIVU
+                0x1F => Ok(Op::AluOp {
+                    op_type: AluOps::DDIVU,
+                    dst: GPR::zero,
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //ADD
+                0x20 => Ok(Op::AluOp {
+                    op_type: AluOps::ADD,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //ADDU
+                0x21 => Ok(Op::AluOp {
+                    op_type: AluOps::ADDU,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //SUB
+                0x22 => Ok(Op::AluOp {
+                    op_type: AluOps::SUB,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //SUBU
+                0x23 => Ok(Op::AluOp {
+                    op_type: AluOps::SUBU,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //AND
+                0x24 => Ok(Op::AluOp {
+                    op_type: AluOps::AND,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //OR
+                0x25 => Ok(Op::AluOp {
+                    op_type: AluOps::OR,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //XOR
+                0x26 => Ok(Op::AluOp {
+                    op_type: AluOps::XOR,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //NOR
+                0x27 => Ok(Op::AluOp {
+                    op_type: AluOps::NOR,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //SLT
+                0x2A => Ok(Op::AluOp {
+                    op_type: AluOps::SLT,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //SLTU
+                0x2B => Ok(Op::AluOp {
+                    op_type: AluOps::SLTU,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DADD
+                0x2C => Ok(Op::AluOp {
+                    op_type: AluOps::DADD,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DADDU
+                0x2D => Ok(Op::AluOp {
+                    op_type: AluOps::DADDU,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DSUB
+                0x2E => Ok(Op::AluOp {
+                    op_type: AluOps::DSUB,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //DSUBU
+                0x2F => Ok(Op::AluOp {
+                    op_type: AluOps::DSUBU,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rs.into()),
+                    src_2: AluOpSrc::Reg(r_op_rt.into()),
+                }),
+                //TGE
+                0x30 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::Ge {
+                            reg2: r_op_rt.into(),
+                        },
+                        register: r_op_rs.into(),
+                    },
+                }),
+                //TGEU
+                0x31 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::GeU {
+                            reg2: r_op_rt.into(),
+                        },
+                        register: r_op_rs.into(),
+                    },
+                }),
+                //TLT
+                0x32 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::Lt {
+                            reg2: r_op_rt.into(),
+                        },
+                        register: r_op_rs.into(),
+                    },
+                }),
+                //TLTU
+                0x33 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::LtU {
+                            reg2: r_op_rt.into(),
+                        },
+                        register: r_op_rs.into(),
+                    },
+                }),
+                //TEQ
+                0x34 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::Eq {
+                            reg2: r_op_rt.into(),
+                        },
+                        register: r_op_rs.into(),
+                    },
                 }),
-                _ => unimplemented!(
-                    "decoded unimplemented opcode in SPECIAL decoding. bit pattern: {:x},  {}",
+                //TNE
+                0x36 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::Ne {
+                            reg2: r_op_rt.into(),
+                        },
+                        register: r_op_rs.into(),
+                    },
+                }),
+                //DSLL
+                0x38 => Ok(Op::AluOp {
+                    op_type: AluOps::DSLL,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Imm(r_op_shamt.into()),
+                }),
+                //DSRL
+                0x3A => Ok(Op::AluOp {
+                    op_type: AluOps::DSRL,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Imm(r_op_shamt.into()),
+                }),
+                //DSRA
+                0x3B => Ok(Op::AluOp {
+                    op_type: AluOps::DSRA,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Imm(r_op_shamt.into()),
+                }),
+                //DSLL32
+                0x3C => Ok(Op::AluOp {
+                    op_type: AluOps::DSLL32,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Imm(r_op_shamt.into()),
+                }),
+                //DSRL32
+                0x3E => Ok(Op::AluOp {
+                    op_type: AluOps::DSRL32,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Imm(r_op_shamt.into()),
+                }),
+                //DSRA32
+                0x3F => Ok(Op::AluOp {
+                    op_type: AluOps::DSRA32,
+                    dst: r_op_rd.into(),
+                    src_1: Some(r_op_rt.into()),
+                    src_2: AluOpSrc::Imm(r_op_shamt.into()),
+                }),
+                //RESERVED INSTRUCTION EXCEPTION
+                0x0A | 0x0B | 0x0E | 0x15 | 0x28 | 0x29 | 0x35 | 0x37 | 0x39 | 0x3D => {
+                    Ok(Op::MalformedOp)
+                }
+                //SPECIAL sub opcode is only 6 bits
+                _ => Err(LiftError::FieldOutOfRange {
                     instr,
-                    disas.disassemble(&[instr])[0]
-                ),
+                    table: DecodeTable::Special,
+                    field: sub_opcode,
+                }),
             }
         }
         //REGIMM decoding
-        //0x1 => {}
+        0x1 => {
+            //for REGIMM the rt field is actually the sub opcode
+            match i_op_rt {
+                //BLTZ
+                0x00 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::LTZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: false,
+                    link: None,
+                }),
+                //BGEZ
+                0x01 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::GEZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: false,
+                    link: None,
+                }),
+                //BLTZL
+                0x02 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::LTZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: true,
+                    link: None,
+                }),
+                //BGEZL
+                0x03 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::GEZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: true,
+                    link: None,
+                }),
+                //TGEI
+                0x08 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::GeI { imm: i_op_imm },
+                        register: i_op_rs.into(),
+                    },
+                }),
+                //TGEIU
+                0x09 => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::GeIU { imm: i_op_imm },
+                        register: i_op_rs.into(),
+                    },
+                }),
+                //TLTI
+                0x0A => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::LtI { imm: i_op_imm },
+                        register: i_op_rs.into(),
+                    },
+                }),
+                //TLTIU
+                0x0B => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::LtIU { imm: i_op_imm },
+                        register: i_op_rs.into(),
+                    },
+                }),
+                //TEQI
+                0x0C => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::EqI { imm: i_op_imm },
+                        register: i_op_rs.into(),
+                    },
+                }),
+                //TNEI
+                0x0E => Ok(Op::System {
+                    opcode: SystemOp::Trap {
+                        condition: ControlConditionalType::NeI { imm: i_op_imm },
+                        register: i_op_rs.into(),
+                    },
+                }),
+                //BLTZAL
+                0x10 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::LTZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: false,
+                    link: Some(GPR::ra),
+                }),
+                //BGEZAL
+                0x11 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::GEZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: false,
+                    link: Some(GPR::ra),
+                }),
+                //BLTZALL
+                0x12 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::LTZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: true,
+                    link: Some(GPR::ra),
+                }),
+                //BGEZALL
+                0x13 => Ok(Op::ControlFlow {
+                    conditional: ControlConditionalType::GEZ,
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+                    likely: true,
+                    link: Some(GPR::ra),
+                }),
+                //RESERVED INSTRUCTION EXCEPTION
+                0x04..=0x07 | 0x0D | 0x0F | 0x14..=0x1F => Ok(Op::MalformedOp),
+                //rt is only 5 bits
+                _ => Err(LiftError::FieldOutOfRange {
+                    instr,
+                    table: DecodeTable::Regimm,
+                    field: i_op_rt.into(),
+                }),
+            }
+        }
+
+        //J
+        0x2 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::Unconditional,
+            destination: ControlDestType::Absolute {
+                dest: ((instr & 0x03FF_FFFF) as usize) << 2,
+            },
+            register: None,
+            likely: false,
+            link: None,
+        }),
+
+        //JAL
+        0x3 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::Unconditional,
+            destination: ControlDestType::Absolute {
+                dest: ((instr & 0x03FF_FFFF) as usize) << 2,
+            },
+            register: None,
+            likely: false,
+            link: Some(GPR::ra),
+        }),
+
+        //BEQ
+        0x4 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::Eq {
+                reg2: i_op_rt.into(),
+            },
+            destination: ControlDestType::Relative {
+                offset: branch_offset,
+            },
+            register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+            likely: false,
+            link: None,
+        }),
 
         //BNE
         0x5 => Ok(Op::ControlFlow {
@@ -325,18 +1088,72 @@ pub fn guest_to_ir(instr: u32) -> Result<Op, LiftError> {
                 reg2: i_op_rt.into(),
             },
             destination: ControlDestType::Relative {
-                offset: i_op_imm.into(),
+                offset: branch_offset,
             },
             register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
             likely: false,
-            link: false,
+            link: None,
+        }),
+
+        //BLEZ
+        0x6 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::LEZ,
+            destination: ControlDestType::Relative {
+                offset: branch_offset,
+            },
+            register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+            likely: false,
+            link: None,
+        }),
+
+        //BGTZ
+        0x7 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::GTZ,
+            destination: ControlDestType::Relative {
+                offset: branch_offset,
+            },
+            register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+            likely: false,
+            link: None,
+        }),
+
+        //ADDI
+        0x8 => Ok(Op::AluOp {
+            op_type: AluOps::ADDI,
+            dst: i_op_rt.into(),
+            src_1: Some(i_op_rs.into()),
+            src_2: AluOpSrc::Imm(i_op_imm),
+        }),
+
+        //ADDIU
+        0x9 => Ok(Op::AluOp {
+            op_type: AluOps::ADDIU,
+            dst: i_op_rt.into(),
+            src_1: Some(i_op_rs.into()),
+            src_2: AluOpSrc::Imm(i_op_imm),
+        }),
+
+        //SLTI
+        0xA => Ok(Op::AluOp {
+            op_type: AluOps::SLTI,
+            dst: i_op_rt.into(),
+            src_1: Some(i_op_rs.into()),
+            src_2: AluOpSrc::Imm(i_op_imm),
+        }),
+
+        //SLTIU
+        0xB => Ok(Op::AluOp {
+            op_type: AluOps::SLTIU,
+            dst: i_op_rt.into(),
+            src_1: Some(i_op_rs.into()),
+            src_2: AluOpSrc::Imm(i_op_imm),
         }),
 
         //ANDI
         0xC => Ok(Op::AluOp {
             op_type: AluOps::ANDI,
             dst: i_op_rt.into(),
-            src_1: Some(i_op_rt.into()),
+            src_1: Some(i_op_rs.into()),
             src_2: AluOpSrc::Imm(i_op_imm),
         }),
 
@@ -348,25 +1165,475 @@ pub fn guest_to_ir(instr: u32) -> Result<Op, LiftError> {
             src_2: AluOpSrc::Imm(i_op_imm),
         }),
 
+        //XORI
+        0xE => Ok(Op::AluOp {
+            op_type: AluOps::XORI,
+            dst: i_op_rt.into(),
+            src_1: Some(i_op_rs.into()),
+            src_2: AluOpSrc::Imm(i_op_imm),
+        }),
+
         //LUI
-        0xF => Ok(Op::Load {
+        0xF => Ok(Op::AluOp {
+            op_type: AluOps::LUI,
+            dst: i_op_rt.into(),
+            src_1: None,
+            src_2: AluOpSrc::Imm(i_op_imm),
+        }),
+
+        //COP0 decoding
+        0x10 => {
+            //bit 25 set means the rest of the instruction is a cop0 function
+            if instr & 0x0200_0000 != 0 {
+                match r_sub_op {
+                    //TLBR
+                    0x01 => Ok(Op::System {
+                        opcode: SystemOp::Tlb { op: TlbOp::Read },
+                    }),
+                    //TLBWI
+                    0x02 => Ok(Op::System {
+                        opcode: SystemOp::Tlb {
+                            op: TlbOp::WriteIndexed,
+                        },
+                    }),
+                    //TLBWR
+                    0x06 => Ok(Op::System {
+                        opcode: SystemOp::Tlb {
+                            op: TlbOp::WriteRandom,
+                        },
+                    }),
+                    //TLBP
+                    0x08 => Ok(Op::System {
+                        opcode: SystemOp::Tlb { op: TlbOp::Probe },
+                    }),
+                    //ERET
+                    0x18 => Ok(Op::System {
+                        opcode: SystemOp::Eret,
+                    }),
+                    //RESERVED INSTRUCTION EXCEPTION
+                    0x00 | 0x03..=0x05 | 0x07 | 0x09..=0x17 | 0x19..=0x3F => {
+                        Ok(Op::ReservedCop { cop: 0 })
+                    }
+                    //the function field is only 6 bits
+                    _ => Err(LiftError::FieldOutOfRange {
+                        instr,
+                        table: DecodeTable::Cop0,
+                        field: r_sub_op.into(),
+                    }),
+                }
+            } else {
+                //otherwise rs picks the move
+                match r_op_rs {
+                    //MFC0
+                    0x00 => Ok(Op::Move {
+                        width: 32,
+                        src: GPRorCoPGPR::cop0(r_op_rd.into()),
+                        dest: GPRorCoPGPR::gpr(r_op_rt.into()),
+                    }),
+                    //DMFC0
+                    0x01 => Ok(Op::Move {
+                        width: 64,
+                        src: GPRorCoPGPR::cop0(r_op_rd.into()),
+                        dest: GPRorCoPGPR::gpr(r_op_rt.into()),
+                    }),
+                    //MTC0
+                    0x04 => Ok(Op::Move {
+                        width: 32,
+                        src: GPRorCoPGPR::gpr(r_op_rt.into()),
+                        dest: GPRorCoPGPR::cop0(r_op_rd.into()),
+                    }),
+                    //DMTC0
+                    0x05 => Ok(Op::Move {
+                        width: 64,
+                        src: GPRorCoPGPR::gpr(r_op_rt.into()),
+                        dest: GPRorCoPGPR::cop0(r_op_rd.into()),
+                    }),
+                    //RESERVED INSTRUCTION EXCEPTION
+                    0x02 | 0x03 | 0x06..=0x0F => Ok(Op::ReservedCop { cop: 0 }),
+                    //with bit 25 clear rs can only go up to 15
+                    _ => Err(LiftError::FieldOutOfRange {
+                        instr,
+                        table: DecodeTable::Cop0,
+                        field: r_op_rs.into(),
+                    }),
+                }
+            }
+        }
+
+        //COP1 decoding
+        0x11 => {
+            //ft, fs and fd sit where rt, rd and shamt would be
+            let (ft, fs, fd) = (r_op_rt, r_op_rd, r_op_shamt);
+            match r_op_rs {
+                //MFC1
+                0x00 => Ok(Op::Move {
+                    width: 32,
+                    src: GPRorCoPGPR::cop1(fs),
+                    dest: GPRorCoPGPR::gpr(r_op_rt.into()),
+                }),
+                //DMFC1
+                0x01 => Ok(Op::Move {
+                    width: 64,
+                    src: GPRorCoPGPR::cop1(fs),
+                    dest: GPRorCoPGPR::gpr(r_op_rt.into()),
+                }),
+                //CFC1
+                0x02 => Ok(Op::Move {
+                    width: 32,
+                    src: GPRorCoPGPR::cop1_control(fs),
+                    dest: GPRorCoPGPR::gpr(r_op_rt.into()),
+                }),
+                //MTC1
+                0x04 => Ok(Op::Move {
+                    width: 32,
+                    src: GPRorCoPGPR::gpr(r_op_rt.into()),
+                    dest: GPRorCoPGPR::cop1(fs),
+                }),
+                //DMTC1
+                0x05 => Ok(Op::Move {
+                    width: 64,
+                    src: GPRorCoPGPR::gpr(r_op_rt.into()),
+                    dest: GPRorCoPGPR::cop1(fs),
+                }),
+                //CTC1
+                0x06 => Ok(Op::Move {
+                    width: 32,
+                    src: GPRorCoPGPR::gpr(r_op_rt.into()),
+                    dest: GPRorCoPGPR::cop1_control(fs),
+                }),
+                //BC1F/BC1T/BC1FL/BC1TL
+                //bit 16 picks true or false, bit 17 makes it a likely
+                0x08 => Ok(Op::ControlFlow {
+                    conditional: if r_op_rt & 0b01 != 0 {
+                        ControlConditionalType::CopZTrue { cop: 1 }
+                    } else {
+                        ControlConditionalType::CopZFalse { cop: 1 }
+                    },
+                    destination: ControlDestType::Relative {
+                        offset: branch_offset,
+                    },
+                    register: None,
+                    likely: r_op_rt & 0b10 != 0,
+                    link: None,
+                }),
+                //S, D, W and L ops. not every op is valid for every format,
+                //execution raises unimplemented operation for the ones that arent
+                0x10 | 0x11 | 0x14 | 0x15 => {
+                    let fmt = match r_op_rs {
+                        0x10 => FpuFormat::S,
+                        0x11 => FpuFormat::D,
+                        0x14 => FpuFormat::W,
+                        _ => FpuFormat::L,
+                    };
+                    let op_type = match r_sub_op {
+                        0x00 => FpuOps::ADD,
+                        0x01 => FpuOps::SUB,
+                        0x02 => FpuOps::MUL,
+                        0x03 => FpuOps::DIV,
+                        0x04 => FpuOps::SQRT,
+                        0x05 => FpuOps::ABS,
+                        0x06 => FpuOps::MOV,
+                        0x07 => FpuOps::NEG,
+                        0x08 => FpuOps::ROUND { to: FpuFormat::L },
+                        0x09 => FpuOps::TRUNC { to: FpuFormat::L },
+                        0x0A => FpuOps::CEIL { to: FpuFormat::L },
+                        0x0B => FpuOps::FLOOR { to: FpuFormat::L },
+                        0x0C => FpuOps::ROUND { to: FpuFormat::W },
+                        0x0D => FpuOps::TRUNC { to: FpuFormat::W },
+                        0x0E => FpuOps::CEIL { to: FpuFormat::W },
+                        0x0F => FpuOps::FLOOR { to: FpuFormat::W },
+                        0x20 => FpuOps::CVT { to: FpuFormat::S },
+                        0x21 => FpuOps::CVT { to: FpuFormat::D },
+                        0x24 => FpuOps::CVT { to: FpuFormat::W },
+                        0x25 => FpuOps::CVT { to: FpuFormat::L },
+                        0x30..=0x3F => FpuOps::C {
+                            cond: r_sub_op & 0xF,
+                        },
+                        //RESERVED INSTRUCTION EXCEPTION
+                        0x10..=0x1F | 0x22 | 0x23 | 0x26..=0x2F => {
+                            return Ok(Op::ReservedCop { cop: 1 })
+                        }
+                        //the function field is only 6 bits
+                        _ => {
+                            return Err(LiftError::FieldOutOfRange {
+                                instr,
+                                table: DecodeTable::Cop1,
+                                field: r_sub_op.into(),
+                            })
+                        }
+                    };
+                    Ok(Op::FpuOp {
+                        op_type,
+                        fmt,
+                        dst: fd,
+                        src_1: fs,
+                        src_2: ft,
+                    })
+                }
+                //RESERVED INSTRUCTION EXCEPTION
+                0x03 | 0x07 | 0x09..=0x0F | 0x12 | 0x13 | 0x16..=0x1F => {
+                    Ok(Op::ReservedCop { cop: 1 })
+                }
+                //rs is only 5 bits
+                _ => Err(LiftError::FieldOutOfRange {
+                    instr,
+                    table: DecodeTable::Cop1,
+                    field: r_op_rs.into(),
+                }),
+            }
+        }
+
+        //COP2 decoding
+        //the moves go through the latch, MTC2 and CTC2 keep the whole 64 bit reg just like DMTC2
+        //everything else, including DCFC2/DCTC2 and BC2, is reserved
+        0x12 => match r_op_rs {
+            //MFC2/CFC2
+            0x00 | 0x02 => Ok(Op::Move {
+                width: 32,
+                src: GPRorCoPGPR::cop2_latch,
+                dest: GPRorCoPGPR::gpr(r_op_rt.into()),
+            }),
+            //DMFC2
+            0x01 => Ok(Op::Move {
+                width: 64,
+                src: GPRorCoPGPR::cop2_latch,
+                dest: GPRorCoPGPR::gpr(r_op_rt.into()),
+            }),
+            //MTC2/DMTC2/CTC2
+            0x04 | 0x05 | 0x06 => Ok(Op::Move {
+                width: 64,
+                src: GPRorCoPGPR::gpr(r_op_rt.into()),
+                dest: GPRorCoPGPR::cop2_latch,
+            }),
+            0x03 | 0x07..=0x1F => Ok(Op::ReservedCop { cop: 2 }),
+            //rs is only 5 bits
+            _ => Err(LiftError::FieldOutOfRange {
+                instr,
+                table: DecodeTable::Cop2,
+                field: r_op_rs.into(),
+            }),
+        },
+
+        //COP3 decoding, nothing here at all
+        0x13 => Ok(Op::ReservedCop { cop: 3 }),
+
+        //BEQL
+        0x14 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::Eq {
+                reg2: i_op_rt.into(),
+            },
+            destination: ControlDestType::Relative {
+                offset: branch_offset,
+            },
+            register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+            likely: true,
+            link: None,
+        }),
+
+        //BNEL
+        0x15 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::Ne {
+                reg2: i_op_rt.into(),
+            },
+            destination: ControlDestType::Relative {
+                offset: branch_offset,
+            },
+            register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+            likely: true,
+            link: None,
+        }),
+
+        //BLEZL
+        0x16 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::LEZ,
+            destination: ControlDestType::Relative {
+                offset: branch_offset,
+            },
+            register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+            likely: true,
+            link: None,
+        }),
+
+        //BGTZL
+        0x17 => Ok(Op::ControlFlow {
+            conditional: ControlConditionalType::GTZ,
+            destination: ControlDestType::Relative {
+                offset: branch_offset,
+            },
+            register: Some(GPRorCoPGPR::gpr(i_op_rs.into())),
+            likely: true,
+            link: None,
+        }),
+
+        //DADDI
+        0x18 => Ok(Op::AluOp {
+            op_type: AluOps::DADDI,
+            dst: i_op_rt.into(),
+            src_1: Some(i_op_rs.into()),
+            src_2: AluOpSrc::Imm(i_op_imm),
+        }),
+
+        //DADDIU
+        0x19 => Ok(Op::AluOp {
+            op_type: AluOps::DADDIU,
+            dst: i_op_rt.into(),
+            src_1: Some(i_op_rs.into()),
+            src_2: AluOpSrc::Imm(i_op_imm),
+        }),
+
+        //LDL
+        0x1A => Ok(Op::Load {
+            width: 64,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: false,
+            left: true,
+        }),
+
+        //LDR
+        0x1B => Ok(Op::Load {
+            width: 64,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: false,
+            left: false,
+        }),
+
+        //LB
+        0x20 => Ok(Op::Load {
+            width: 8,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: true,
+            left: false,
+        }),
+
+        //LH
+        0x21 => Ok(Op::Load {
             width: 16,
+            signed: true,
             dest: GPRorCoPGPR::gpr(i_op_rt.into()),
-            base: None,
-            offset: None,
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
             condtional: false,
             aligned: true,
-            imm_src: Some((i_op_imm as usize) << 16),
+            left: false,
+        }),
+
+        //LWL
+        0x22 => Ok(Op::Load {
+            width: 32,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: false,
+            left: true,
         }),
 
         //LW
         0x23 => Ok(Op::Load {
             width: 32,
+            signed: true,
             dest: GPRorCoPGPR::gpr(i_op_rt.into()),
             base: Some(i_op_rs.into()),
             offset: Some(i_op_imm),
             condtional: false,
             aligned: true,
+            left: false,
+        }),
+
+        //LBU
+        0x24 => Ok(Op::Load {
+            width: 8,
+            signed: false,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: true,
+            left: false,
+        }),
+
+        //LHU
+        0x25 => Ok(Op::Load {
+            width: 16,
+            signed: false,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: true,
+            left: false,
+        }),
+
+        //LWR
+        0x26 => Ok(Op::Load {
+            width: 32,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: false,
+            left: false,
+        }),
+
+        //LWU
+        0x27 => Ok(Op::Load {
+            width: 32,
+            signed: false,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: true,
+            left: false,
+        }),
+
+        //SB
+        0x28 => Ok(Op::Store {
+            width: 8,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: true,
+            left: false,
+            imm_src: None,
+        }),
+
+        //SH
+        0x29 => Ok(Op::Store {
+            width: 16,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: true,
+            left: false,
+            imm_src: None,
+        }),
+
+        //SWL
+        0x2A => Ok(Op::Store {
+            width: 32,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: false,
+            left: true,
             imm_src: None,
         }),
 
@@ -378,14 +1645,211 @@ pub fn guest_to_ir(instr: u32) -> Result<Op, LiftError> {
             offset: Some(i_op_imm),
             conditional: false,
             aligned: true,
+            left: false,
             imm_src: None,
         }),
-        _ => unimplemented!(
-            "decoded unimplemented opcode in main decoding. bit pattern: {:x},  {}",
+
+        //SDL
+        0x2C => Ok(Op::Store {
+            width: 64,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: false,
+            left: true,
+            imm_src: None,
+        }),
+
+        //SDR
+        0x2D => Ok(Op::Store {
+            width: 64,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: false,
+            left: false,
+            imm_src: None,
+        }),
+
+        //SWR
+        0x2E => Ok(Op::Store {
+            width: 32,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: false,
+            left: false,
+            imm_src: None,
+        }),
+
+        //CACHE
+        //rt holds the cache in bits 1..0 and the op in bits 4..2
+        0x2F => {
+            let data = i_op_rt & 0b11 == 1;
+            let op = match (i_op_rt >> 2, i_op_rt & 0b11) {
+                (_, 2..=3) => CacheOp::Undefined,
+                (0, _) => CacheOp::IndexInvalidate { data },
+                (1, _) => CacheOp::IndexLoadTag { data },
+                (2, _) => CacheOp::IndexStoreTag { data },
+                (3, 1) => CacheOp::CreateDirtyExclusive,
+                (4, _) => CacheOp::HitInvalidate { data },
+                (5, 0) => CacheOp::Fill,
+                (5, 1) => CacheOp::HitWriteBackInvalidate,
+                (6, _) => CacheOp::HitWriteBack { data },
+                _ => CacheOp::Undefined,
+            };
+            Ok(Op::System {
+                opcode: SystemOp::Cache {
+                    op,
+                    base: i_op_rs.into(),
+                    offset: i_op_imm,
+                },
+            })
+        }
+
+        //LL
+        0x30 => Ok(Op::Load {
+            width: 32,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: true,
+            aligned: true,
+            left: false,
+        }),
+
+        //LWC1
+        0x31 => Ok(Op::Load {
+            width: 32,
+            signed: false,
+            dest: GPRorCoPGPR::cop1(i_op_rt),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: true,
+            left: false,
+        }),
+
+        //LWC2
+        0x32 => Ok(Op::ReservedCop { cop: 2 }),
+
+        //LLD
+        0x34 => Ok(Op::Load {
+            width: 64,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: true,
+            aligned: true,
+            left: false,
+        }),
+
+        //LDC1
+        0x35 => Ok(Op::Load {
+            width: 64,
+            signed: false,
+            dest: GPRorCoPGPR::cop1(i_op_rt),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: true,
+            left: false,
+        }),
+
+        //LDC2
+        0x36 => Ok(Op::ReservedCop { cop: 2 }),
+
+        //LD
+        0x37 => Ok(Op::Load {
+            width: 64,
+            signed: true,
+            dest: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            condtional: false,
+            aligned: true,
+            left: false,
+        }),
+
+        //SC
+        0x38 => Ok(Op::Store {
+            width: 32,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: true,
+            aligned: true,
+            left: false,
+            imm_src: None,
+        }),
+
+        //SWC1
+        0x39 => Ok(Op::Store {
+            width: 32,
+            src: GPRorCoPGPR::cop1(i_op_rt),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: true,
+            left: false,
+            imm_src: None,
+        }),
+
+        //SWC2
+        0x3A => Ok(Op::ReservedCop { cop: 2 }),
+
+        //SCD
+        0x3C => Ok(Op::Store {
+            width: 64,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: true,
+            aligned: true,
+            left: false,
+            imm_src: None,
+        }),
+
+        //SDC1
+        0x3D => Ok(Op::Store {
+            width: 64,
+            src: GPRorCoPGPR::cop1(i_op_rt),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: true,
+            left: false,
+            imm_src: None,
+        }),
+
+        //SDC2
+        0x3E => Ok(Op::ReservedCop { cop: 2 }),
+
+        //SD
+        0x3F => Ok(Op::Store {
+            width: 64,
+            src: GPRorCoPGPR::gpr(i_op_rt.into()),
+            base: Some(i_op_rs.into()),
+            offset: Some(i_op_imm),
+            conditional: false,
+            aligned: true,
+            left: false,
+            imm_src: None,
+        }),
+        //RESERVED INSTRUCTION EXCEPTION
+        //the old LWC3/SWC3 slots dont even check CU3
+        0x1C..=0x1F | 0x33 | 0x3B => Ok(Op::MalformedOp),
+
+        //the opcode is only 6 bits
+        _ => Err(LiftError::FieldOutOfRange {
             instr,
-            disas.disassemble(&[instr])[0]
-        ),
+            table: DecodeTable::Main,
+            field: opcode,
+        }),
     };
-
-    //Err(LiftError::InvalidOpcodeError)
 }
@@ -5,14 +5,18 @@ use std::thread::Builder;
 
 use crate::system::System;
 
+mod cache;
 mod cart;
 mod cpu;
+mod fpu;
 mod ir;
+mod mi;
 mod pi;
 mod rcp;
 mod rdram;
 mod rsp;
 mod system;
+mod tlb;
 
 fn main() {
     pretty_env_logger::init();
@@ -0,0 +1,91 @@
+use log::trace;
+
+use crate::rcp::merge_store;
+
+//MIPS interface, collects the interrupts from every rcp block onto the cpu's IP2
+//each source has a bit in MI_INTR and only the ones unmasked in MI_INTR_MASK reach the cpu
+#[derive(Debug, Default, Copy, Clone)]
+#[allow(non_snake_case)]
+pub struct MI {
+    pub MI_MODE: u32,
+    pub MI_INTR: u32,
+    pub MI_INTR_MASK: u32,
+}
+
+//MI_INTR/MI_INTR_MASK bits, SP SI AI VI PI DP from bit 0 up
+pub const MI_INTR_PI: u32 = 1 << 4;
+pub const MI_INTR_DP: u32 = 1 << 5;
+
+//rsp 2.0, rdp 1.0, rac 2.0, io 2.0 as reported by retail consoles
+const MI_VERSION: u32 = 0x0202_0102;
+
+impl MI {
+    pub fn read(&self, addr: u32, len: usize) -> Result<Vec<u8>, String> {
+        trace!("in MI read: addr: {addr:#x}, len: {:?}", len);
+
+        //the registers mirror every 16 bytes, narrower loads just get their lane
+        let word = match addr & 0xC {
+            0x0 => self.MI_MODE,
+            0x4 => MI_VERSION,
+            0x8 => self.MI_INTR,
+            _ => self.MI_INTR_MASK,
+        };
+        let offset = (addr & 3) as usize;
+        let end = (offset + len).min(4);
+        Ok(word.to_be_bytes()[offset..end].to_vec())
+    }
+
+    pub fn write(&mut self, addr: u32, val: Vec<u8>) {
+        trace!("in MI write: addr: {addr:#x}, val: {:?}", val);
+
+        let val = merge_store(0, (addr & 3) as usize, &val);
+        match addr & 0xC {
+            //MI_MODE
+            //the low 7 bits are the init length, then pairs of clear/set bits for init mode,
+            //ebus test mode and rdram register mode. bit 11 acknowledges the DP interrupt
+            0x0 => {
+                let mut mode = (self.MI_MODE & !0x7F) | (val & 0x7F);
+                for (bit, clear, set) in [(7, 7, 8), (8, 9, 10), (9, 12, 13)] {
+                    if val & (1 << clear) != 0 {
+                        mode &= !(1 << bit);
+                    }
+                    if val & (1 << set) != 0 {
+                        mode |= 1 << bit;
+                    }
+                }
+                self.MI_MODE = mode;
+                if val & (1 << 11) != 0 {
+                    self.MI_INTR &= !MI_INTR_DP;
+                }
+            }
+            //MI_INTR_MASK
+            //a clear/set bit pair for every source, SP in bits 0/1 up to DP in bits 10/11
+            0xC => {
+                for source in 0..6 {
+                    if val & (1 << (source * 2)) != 0 {
+                        self.MI_INTR_MASK &= !(1 << source);
+                    }
+                    if val & (1 << (source * 2 + 1)) != 0 {
+                        self.MI_INTR_MASK |= 1 << source;
+                    }
+                }
+            }
+            //MI_VERSION and MI_INTR are read only
+            _ => {}
+        }
+    }
+
+    //the rcp blocks raise and lower their own bit, acknowledging happens in the block itself
+    pub fn set_interrupt(&mut self, source: u32, raised: bool) {
+        if raised {
+            self.MI_INTR |= source;
+        } else {
+            self.MI_INTR &= !source;
+        }
+    }
+
+    //what the cpu sees on IP2
+    pub fn interrupt(&self) -> bool {
+        self.MI_INTR & self.MI_INTR_MASK != 0
+    }
+}
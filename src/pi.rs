@@ -3,6 +3,8 @@ use std::fmt::{Display, Write};
 use log::{trace, warn};
 use proc_bitfield::bitfield;
 
+use crate::rcp::merge_store;
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct PI {
     pub PI_DRAM_ADDR: u32,
@@ -71,7 +73,7 @@ impl PI {
             //PI_STATUS
             0x0460_0010 => {
                 let val: u32 = self.PI_STATUS.into();
-                return Ok(val.to_le_bytes().to_vec());
+                return Ok(val.to_be_bytes().to_vec());
             }
             _ => {
                 panic!("panicking in PI read on a bad address within PI MMIO range. probably have not implemented reading this reg yet")
@@ -84,23 +86,15 @@ impl PI {
         if !(0x0460_0000..=0x0460_0030).contains(&addr) {
             panic!("attempting to write to a non mmio-address in PI {addr:#x}")
         }
-        if val.len() != 4 {
-            panic!("attempting to write more than 32 bits in PI. is this behavior you really really actually want?")
-        }
-        match addr {
+        //stores narrower than a word only replace their own lane of the register
+        let offset = (addr & 3) as usize;
+        match addr & !3 {
             0x0460_0000 => {
-                self.PI_DRAM_ADDR =
-                    val[0] as u32 | (val[1] as u32) << 8 | (val[2] as u32) << 16 | (0 as u32) << 24
-            }
-            0x0460_0004 => {
-                self.PI_CART_ADDR = val[0] as u32
-                    | (val[1] as u32) << 8
-                    | (val[2] as u32) << 16
-                    | (val[3] as u32) << 24
+                self.PI_DRAM_ADDR = merge_store(self.PI_DRAM_ADDR, offset, &val) & 0x00FF_FFFF
             }
+            0x0460_0004 => self.PI_CART_ADDR = merge_store(self.PI_CART_ADDR, offset, &val),
             0x0460_0008 => {
-                self.PI_RD_LEN =
-                    val[0] as u32 | (val[1] as u32) << 8 | (val[2] as u32) << 16 | (0 as u32) << 24;
+                self.PI_RD_LEN = merge_store(self.PI_RD_LEN, offset, &val) & 0x00FF_FFFF;
 
                 return Some(DMA_transfer_command {
                     from: self.PI_DRAM_ADDR,
@@ -109,8 +103,7 @@ impl PI {
                 });
             }
             0x0460_000C => {
-                self.PI_WR_LEN =
-                    val[0] as u32 | (val[1] as u32) << 8 | (val[2] as u32) << 16 | (0 as u32) << 24;
+                self.PI_WR_LEN = merge_store(self.PI_WR_LEN, offset, &val) & 0x00FF_FFFF;
 
                 return Some(DMA_transfer_command {
                     from: self.PI_CART_ADDR,
@@ -118,12 +111,28 @@ impl PI {
                     len: (self.PI_WR_LEN + 1) as usize,
                 });
             }
+            //PI_STATUS
+            //bit 1 acknowledges the dma interrupt, bit 0 would reset the controller
+            0x0460_0010 => {
+                if merge_store(0, offset, &val) & 0b10 != 0 {
+                    self.PI_STATUS &= !0b1000;
+                }
+            }
             _ => {
                 unreachable!("YOU SHOULD NEVER GET HERE. HOW DID U GET HERE.")
             }
         }
         None
     }
+
+    //a finished dma raises the PI interrupt, bit 3 of PI_STATUS, until its acknowledged
+    pub fn dma_complete(&mut self) {
+        self.PI_STATUS |= 0b1000;
+    }
+
+    pub fn interrupt(&self) -> bool {
+        self.PI_STATUS & 0b1000 != 0
+    }
 }
 
 /*
@@ -14,3 +14,12 @@ impl Rcp {
         }
     }
 }
+
+//merges a store narrower than a word into a 32 bit register
+//offset is the byte within the word the store starts at, bytes are big endian like the rest of the bus
+pub fn merge_store(old: u32, offset: usize, data: &[u8]) -> u32 {
+    let mut bytes = old.to_be_bytes();
+    let len = data.len().min(4 - offset);
+    bytes[offset..offset + len].copy_from_slice(&data[..len]);
+    u32::from_be_bytes(bytes)
+}
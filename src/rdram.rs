@@ -2,6 +2,8 @@ use std::fmt::format;
 
 use log::debug;
 
+use crate::rcp::merge_store;
+
 pub struct Rdram {
     mem: Vec<u8>,
     RI_MODE: u32,
@@ -10,6 +12,9 @@ pub struct Rdram {
     RI_SELECT: u32,
     RI_REFRESH: u32,
     RI_LATENCY: u32,
+    //the rdram chips own config registers. every chip gets configured the same way so we only
+    //keep one set, and a write to any chip (or a broadcast) lands here
+    rdram_regs: [u32; 10],
 }
 
 /*impl Rdram {
@@ -25,13 +30,14 @@ pub struct Rdram {
 impl Default for Rdram {
     fn default() -> Self {
         return Rdram {
-            mem: Vec::with_capacity(4194304),
+            mem: vec![0; 4194304],
             RI_SELECT: 0x14,
             RI_MODE: 0,
             RI_CONFIG: 0,
             RI_CURRENT_LOAD: 0,
             RI_LATENCY: 0,
             RI_REFRESH: 0,
+            rdram_regs: [0; 10],
         };
     }
 }
@@ -39,112 +45,64 @@ impl Default for Rdram {
 //read and writes come in from the cpu's addressing space
 impl Rdram {
     pub fn read(&self, addr: u32, len: usize) -> Result<Vec<u8>, String> {
-        if (0x0470_0000..=0x470_0014).contains(&addr) {
+        if (0x0470_0000..=0x047F_FFFF).contains(&addr) {
             //this is reading a RDRAM INTERFACE config register
-            match addr {
-                0x0470_0000 => Ok(self.RI_MODE.to_be_bytes().to_vec()),
-                0x0470_00004 => Ok(self.RI_CONFIG.to_be_bytes().to_vec()),
-                0x0470_00008 => Ok(self.RI_CURRENT_LOAD.to_be_bytes().to_vec()),
-                0x0470_0000C => Ok(self.RI_SELECT.to_be_bytes().to_vec()),
-                0x0470_00010 => Ok(self.RI_REFRESH.to_be_bytes().to_vec()),
-                0x0470_00014 => Ok(self.RI_LATENCY.to_be_bytes().to_vec()),
-                _ => unreachable!("how the fuck did u get here"),
-            }
-        } else if (0x0000_0000..=0x03FF_FFFF).contains(&addr) {
+            //narrower loads just get their lane of the word
+            let offset = (addr & 3) as usize;
+            let word = self.ri_reg(addr).copied().unwrap_or(0);
+            let end = (offset + len).min(4);
+            Ok(word.to_be_bytes()[offset..end].to_vec())
+        } else if (0x03F0_0000..=0x03FF_FFFF).contains(&addr) {
+            //the broadcast half is write only
+            let offset = (addr & 3) as usize;
+            let word = match addr {
+                0x03F0_0000..=0x03F7_FFFF => self.rdram_regs.get(Self::rdram_reg(addr)),
+                _ => None,
+            };
+            let end = (offset + len).min(4);
+            Ok(word.copied().unwrap_or(0).to_be_bytes()[offset..end].to_vec())
+        } else if (0x0000_0000..=0x03EF_FFFF).contains(&addr) {
             //this is reading somewhere actually within rdram
+            let final_addr = Self::mem_offset(addr) as usize;
 
-            if (0x03F0_0000..=0x03F7_FFFF).contains(&addr)
-                || (0x03F8_0000..=0x03FF_FFFF).contains(&addr)
-            {
-                unimplemented!(
-                    "physical rdram register read access not implemented: {:#x}",
-                    addr
-                )
+            //anything past the end of the installed memory reads back as zero, which is what
+            //the size probing in the boot code expects to see
+            let mut data = vec![0; len];
+            if final_addr < self.mem.len() {
+                let end = (final_addr + len).min(self.mem.len());
+                data[..end - final_addr].copy_from_slice(&self.mem[final_addr..end]);
             }
-
-            let final_addr = 0b0000000 << 29
-                | ((addr >> 20) & 0x3F) << 20
-                | ((addr >> 11) & 0x1FF) << 11
-                | (addr & 0x7FF);
-
-            return Ok(self.mem[(final_addr as usize)..(final_addr as usize) + len].to_vec());
+            Ok(data)
         } else {
             Err(format!("trying to read a region not within RI {:#x}", addr).to_string())
         }
     }
 
     pub fn write(&mut self, addr: u32, data: Vec<u8>) -> Result<usize, String> {
-        if (0x0470_0000..=0x470_0014).contains(&addr) {
+        if (0x0470_0000..=0x047F_FFFF).contains(&addr) {
             //this is writing a RDRAM INTERFACE config register
-            match addr {
-                0x0470_0000 => {
-                    self.RI_MODE = (data[0] as u32
-                        | (data[1] as u32) << 8
-                        | (data[2] as u32) << 16
-                        | (data[3] as u32) << 24) as u32;
-                    Ok(32)
-                }
-                0x0470_00004 => {
-                    self.RI_CONFIG = (data[0] as u32
-                        | (data[1] as u32) << 8
-                        | (data[2] as u32) << 16
-                        | (data[3] as u32) << 24) as u32;
-                    Ok(32)
-                }
-                0x0470_00008 => {
-                    self.RI_CURRENT_LOAD = (data[0] as u32
-                        | (data[1] as u32) << 8
-                        | (data[2] as u32) << 16
-                        | (data[3] as u32) << 24) as u32;
-                    Ok(32)
-                }
-                0x0470_0000C => {
-                    self.RI_SELECT = (data[0] as u32
-                        | (data[1] as u32) << 8
-                        | (data[2] as u32) << 16
-                        | (data[3] as u32) << 24) as u32;
-                    Ok(32)
-                }
-                0x0470_00010 => {
-                    self.RI_REFRESH = (data[0] as u32
-                        | (data[1] as u32) << 8
-                        | (data[2] as u32) << 16
-                        | (data[3] as u32) << 24) as u32;
-                    Ok(32)
-                }
-                0x0470_00014 => {
-                    self.RI_LATENCY = (data[0] as u32
-                        | (data[1] as u32) << 8
-                        | (data[2] as u32) << 16
-                        | (data[3] as u32) << 24) as u32;
-                    Ok(32)
-                }
-                _ => unreachable!("how the fuck did u get here"),
+            //stores narrower than a word only replace their own lane
+            let offset = (addr & 3) as usize;
+            if let Some(reg) = self.ri_reg_mut(addr) {
+                *reg = merge_store(*reg, offset, &data);
             }
-        } else if (0x0000_0000..=0x03FF_FFFF).contains(&addr) {
-            //this is writing somewhere actually within rdram
-            if (0x03F0_0000..=0x03F7_FFFF).contains(&addr)
-                || (0x03F8_0000..=0x03FF_FFFF).contains(&addr)
-            {
-                unimplemented!(
-                    "physical rdram register write access not implemented: {:#x}",
-                    addr
-                )
+            Ok(data.len())
+        } else if (0x03F0_0000..=0x03FF_FFFF).contains(&addr) {
+            let offset = (addr & 3) as usize;
+            if let Some(reg) = self.rdram_regs.get_mut(Self::rdram_reg(addr)) {
+                *reg = merge_store(*reg, offset, &data);
             }
-
-            let final_addr = 0b0000000 << 29
-                | ((addr >> 20) & 0x3F) << 20
-                | ((addr >> 11) & 0x1FF) << 11
-                | (addr & 0x7FF);
+            Ok(data.len())
+        } else if (0x0000_0000..=0x03EF_FFFF).contains(&addr) {
+            //this is writing somewhere actually within rdram
+            let final_addr = Self::mem_offset(addr) as usize;
 
             debug!("in rdram::write, final_addr is: {:#x}", final_addr);
 
-            //TODO: check your math here i have no idea if youre adding the proper offset lol
-            unsafe {
-                let src_ptr = data.as_ptr();
-                let mut dst_ptr = self.mem.as_mut_ptr();
-                dst_ptr = dst_ptr.add(final_addr as usize);
-                std::ptr::copy_nonoverlapping(src_ptr, dst_ptr, data.len());
+            //writes past the end of the installed memory go nowhere
+            if final_addr < self.mem.len() {
+                let end = (final_addr + data.len()).min(self.mem.len());
+                self.mem[final_addr..end].copy_from_slice(&data[..end - final_addr]);
             }
 
             return Ok(data.len());
@@ -152,4 +110,40 @@ impl Rdram {
             Err(format!("trying to write a region not within RI {:#x}", addr).to_string())
         }
     }
+
+    //the RI registers mirror every 32 bytes. RI_RERROR and RI_WERROR at 0x18/0x1C arent kept,
+    //nothing here ever raises a bus error for them to report
+    fn ri_reg(&self, addr: u32) -> Option<&u32> {
+        match addr & 0x1C {
+            0x00 => Some(&self.RI_MODE),
+            0x04 => Some(&self.RI_CONFIG),
+            0x08 => Some(&self.RI_CURRENT_LOAD),
+            0x0C => Some(&self.RI_SELECT),
+            0x10 => Some(&self.RI_REFRESH),
+            0x14 => Some(&self.RI_LATENCY),
+            _ => None,
+        }
+    }
+
+    fn ri_reg_mut(&mut self, addr: u32) -> Option<&mut u32> {
+        match addr & 0x1C {
+            0x00 => Some(&mut self.RI_MODE),
+            0x04 => Some(&mut self.RI_CONFIG),
+            0x08 => Some(&mut self.RI_CURRENT_LOAD),
+            0x0C => Some(&mut self.RI_SELECT),
+            0x10 => Some(&mut self.RI_REFRESH),
+            0x14 => Some(&mut self.RI_LATENCY),
+            _ => None,
+        }
+    }
+
+    //DeviceType, DeviceId, Delay, Mode, RefInterval, RefRow, RasInterval, MinInterval,
+    //AddressSelect and DeviceManufacturer, one word each from the start of a chips register space
+    fn rdram_reg(addr: u32) -> usize {
+        ((addr & 0x3FF) >> 2) as usize
+    }
+
+    fn mem_offset(addr: u32) -> u32 {
+        ((addr >> 20) & 0x3F) << 20 | ((addr >> 11) & 0x1FF) << 11 | (addr & 0x7FF)
+    }
 }
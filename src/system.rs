@@ -1,11 +1,16 @@
+use crate::cache::{DCache, ICache, DCACHE_LINE, ICACHE_LINE};
 use crate::cart::Cart;
 use crate::cpu::Cpu;
+use crate::cpu::Exception;
 use crate::cpu::GPR;
+use crate::fpu::{self, RoundingMode};
 use crate::ir::ControlConditionalType;
-use crate::ir::{AluOps::*, Op};
+use crate::ir::{AluOpSrc, AluOps, AluOps::*, CacheOp, FpuFormat, FpuOps, Op, SystemOp, TlbOp};
+use crate::mi::{MI, MI_INTR_PI};
 use crate::pi::PI;
 use crate::rcp::Rcp;
 use crate::rdram::Rdram;
+use crate::tlb::TlbMiss;
 use colored::Colorize;
 use log::trace;
 use log::{debug, info};
@@ -24,7 +29,12 @@ pub struct System {
     pub cart: Rc<RefCell<Cart>>,
     pub rcp: Rc<RefCell<Rcp>>,
     pub pi: Rc<RefCell<PI>>,
+    pub mi: Rc<RefCell<MI>>,
     pub rdram: Rc<RefCell<Rdram>>,
+
+    //lets a debugger treat BREAK as a software breakpoint. gets the address and code of the BREAK,
+    //returning true means the debugger took it and the guest never sees the exception
+    pub break_hook: Option<Box<dyn FnMut(u64, u32) -> bool>>,
 }
 
 #[derive(Debug)]
@@ -50,8 +60,40 @@ const BRANCH_OR_JUMP_OPS: [u8; 17] = [
     0b0000_00, //JALR (special), JR
 ];
 
+//what a memory access is for, translation and the caches care about the difference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemAccess {
+    Fetch,
+    Load,
+    Store,
+}
+
+//AdEL covers fetches as well as loads
+fn address_error(vaddr: u64, access: MemAccess) -> ExecutionError {
+    ExecutionError::Exception(match access {
+        MemAccess::Store => Exception::AddressErrorStore { vaddr },
+        MemAccess::Fetch | MemAccess::Load => Exception::AddressErrorLoad { vaddr },
+    })
+}
+
+//the rcp ignores everything from 0x8000_0000 up and never answers, which freezes a real console
+//we raise a bus error instead so the access at least shows up somewhere
+fn check_bus(phys: u32, access: MemAccess) -> Result<(), ExecutionError> {
+    if phys < 0x8000_0000 {
+        return Ok(());
+    }
+    log::error!("{access:?} of {phys:#x}, which the rcp ignores");
+    Err(ExecutionError::Exception(match access {
+        MemAccess::Fetch => Exception::BusErrorInstruction,
+        MemAccess::Load | MemAccess::Store => Exception::BusErrorData,
+    }))
+}
+
 #[derive(Debug)]
-pub enum ExecutionError {}
+pub enum ExecutionError {
+    //the op faulted and the cpu needs to take this exception instead of retiring it
+    Exception(Exception),
+}
 
 impl System {
     pub fn run(&mut self) -> Result<SystemResult, SystemResult> {
@@ -88,7 +130,13 @@ impl System {
 
         loop {
             let block_base = self.cpu.borrow().rf.PC;
-            let block = self.find_next_basic_block();
+            let block = match self.find_next_basic_block() {
+                Ok(block) => block,
+                Err(ExecutionError::Exception(e)) => {
+                    self.cpu.borrow_mut().exception(e);
+                    continue;
+                }
+            };
             println!("found basic block:");
             for (idx, instr) in block.iter().enumerate() {
                 println!(
@@ -99,63 +147,136 @@ impl System {
                     disas.disassemble(&[instr.1])[0]
                 );
             }
-            let ir_block = crate::ir::lift(block);
-            for op in &ir_block {
-                self.execute_IR(op.0.clone(), op.1).unwrap();
-                self.cpu.borrow_mut().rf.PC += 4;
+            let ir_block = match crate::ir::lift(block) {
+                Ok(ir_block) => ir_block,
+                Err(e) => {
+                    log::error!("lifter bug: {:?}", e);
+                    return Err(SystemResult::Errored);
+                }
+            };
+            for (op, addr) in ir_block {
+                //a taken branch or a nullified delay slot can send us off this block early,
+                //so only keep going while PC still lines up with it
+                if self.cpu.borrow().rf.PC != addr {
+                    break;
+                }
+                self.step(op, addr);
             }
-            drop(ir_block)
         }
 
         Ok(SystemResult::Graceful)
     }
 
+    //executes a single op and then moves PC on to whatever comes after it
+    //interrupts get checked here first, so they land on instruction boundaries
+    pub fn step(&mut self, op: Op, addr: u64) {
+        if self.cpu.borrow().interrupt_pending() {
+            self.cpu.borrow_mut().exception(Exception::Interrupt);
+            return;
+        }
+
+        match self.execute_IR(op, addr) {
+            Ok(cycles) => {
+                let mut cpu = self.cpu.borrow_mut();
+                cpu.advance_pc();
+                cpu.tick(cycles);
+            }
+            Err(ExecutionError::Exception(e)) => {
+                let mut cpu = self.cpu.borrow_mut();
+                cpu.exception(e);
+                cpu.tick(1);
+            }
+        }
+    }
+
     pub fn execute_IR(&mut self, op: Op, addr: u64) -> Result<usize, ExecutionError> {
         info!(
             "{}",
             format!("executing ir op {:#x},{:?}", addr, op).green()
         );
+
+        //coprocessor ops check Status.CU before anything else
+        if let Some(cop) = op.coprocessor() {
+            if !self.cpu.borrow().coprocessor_usable(cop) {
+                return Err(ExecutionError::Exception(Exception::CoprocessorUnusable {
+                    cop,
+                }));
+            }
+        }
+
+        //doubleword ops only exist in kernel mode or with 64 bit addressing on
+        if op.is_64bit() {
+            let cpu = self.cpu.borrow();
+            if cpu.privilege_level() != 0 && !cpu.is_64bit_addressing() {
+                return Err(ExecutionError::Exception(Exception::ReservedInstruction));
+            }
+        }
+
         match op {
             Op::Load {
                 width,
+                signed,
                 dest,
                 base,
                 offset,
                 condtional,
                 aligned,
-                imm_src,
+                left,
             } => {
-                if aligned == false {
-                    unimplemented!("implement unaligned memory accesses")
-                }
-                if condtional == true {
-                    unimplemented!("implement load conditional")
+                if !aligned {
+                    return match dest {
+                        crate::ir::GPRorCoPGPR::gpr(r) => {
+                            self.execute_unaligned_load(width, left, r, base.unwrap(), offset)
+                        }
+                        _ => {
+                            unreachable!("there are no unaligned coprocessor loads")
+                        }
+                    };
                 }
 
                 //calculate the value we are loading
-                let value = match base {
-                    Some(r) => {
-                        let base_addr = self.cpu.borrow_mut().rf[r];
-                        let final_addr = base_addr + offset.unwrap_or(0) as u64;
-                        let bytes = self.read(final_addr as u32, width / 8).unwrap();
-
-                        if width != 32 {
-                            panic!("chris you gotta go implement reads for multiple widths")
-                        }
-                        bytes[0] as u32
-                            | (bytes[1] as u32) << 8
-                            | (bytes[2] as u32) << 16
-                            | (bytes[3] as u32) << 24
-                    }
-                    None => imm_src.unwrap() as u32,
+                let final_addr = self.effective_address(base.unwrap(), offset);
+                if !final_addr.is_multiple_of((width / 8) as u64) {
+                    return Err(address_error(final_addr, MemAccess::Load));
+                }
+                let bytes = self.read(self.swizzle(final_addr, width / 8), width / 8)?;
+
+                //LL/LLD open a link that a later SC/SCD checks
+                //LLAddr holds bits 35..4 of the physical address
+                if condtional {
+                    let phys = self.virt_to_phys(final_addr, MemAccess::Load)?;
+                    let mut cpu = self.cpu.borrow_mut();
+                    cpu.rf.LLBit = true;
+                    cpu.cop0.LLAddr = phys >> 4;
+                }
+
+                //everything on this bus is big endian
+                let value = bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+
+                //extend to the full 64 bit reg
+                let value = match (width, signed) {
+                    (8, true) => value as i8 as u64,
+                    (16, true) => value as i16 as u64,
+                    (32, true) => value as i32 as u64,
+                    (8, false) => value as u8 as u64,
+                    (16, false) => value as u16 as u64,
+                    (32, false) => value as u32 as u64,
+                    (64, _) => value,
+                    _ => unreachable!("bad load width {}", width),
                 };
 
                 //write to destination
                 match dest {
-                    crate::ir::GPRorCoPGPR::gpr(r) => self.cpu.borrow_mut().rf[r] = value as u64,
-                    crate::ir::GPRorCoPGPR::cop => {
-                        unimplemented!("implement load to coprocessor")
+                    crate::ir::GPRorCoPGPR::gpr(r) => self.cpu.borrow_mut().rf[r] = value,
+                    //LWC1/LDC1
+                    crate::ir::GPRorCoPGPR::cop1(r) if width == 32 => {
+                        self.cpu.borrow_mut().write_fpr_word(r, value as u32)
+                    }
+                    crate::ir::GPRorCoPGPR::cop1(r) => {
+                        self.cpu.borrow_mut().write_fpr_dword(r, value)
                     }
+                    //LWC2/LDC2 are lifted as ReservedCop and cop0 has no loads
+                    _ => unreachable!("loads only target gprs and cop1"),
                 }
             }
             Op::Store {
@@ -165,32 +286,57 @@ impl System {
                 offset,
                 conditional,
                 aligned,
+                left,
                 imm_src,
             } => {
-                if aligned == false {
-                    unimplemented!("implement unaligned memory accesses in store")
-                }
-                if conditional == true {
-                    unimplemented!("implement store conditional")
-                }
-                if width != 32 {
-                    unimplemented!("implement stores for variable width")
+                if !aligned {
+                    return match src {
+                        crate::ir::GPRorCoPGPR::gpr(r) => {
+                            self.execute_unaligned_store(width, left, r, base.unwrap(), offset)
+                        }
+                        _ => {
+                            unreachable!("there are no unaligned coprocessor stores")
+                        }
+                    };
                 }
 
                 let val = if imm_src.is_none() {
                     match src {
-                        crate::ir::GPRorCoPGPR::gpr(r) => self.cpu.borrow_mut().rf[r] as u32,
-                        crate::ir::GPRorCoPGPR::cop => {
-                            unimplemented!("implement stores from coprocessor")
+                        crate::ir::GPRorCoPGPR::gpr(r) => self.cpu.borrow().rf[r],
+                        //SWC1/SDC1
+                        crate::ir::GPRorCoPGPR::cop1(r) if width == 32 => {
+                            self.cpu.borrow().read_fpr_word(r) as u64
                         }
+                        crate::ir::GPRorCoPGPR::cop1(r) => self.cpu.borrow().read_fpr_dword(r),
+                        //SWC2/SDC2 are lifted as ReservedCop and cop0 has no stores
+                        _ => unreachable!("stores only come from gprs and cop1"),
                     }
                 } else {
-                    imm_src.unwrap() as u32
+                    imm_src.unwrap() as u64
                 };
 
-                let address =
-                    self.cpu.borrow_mut().rf[base.unwrap()] as u32 + offset.unwrap_or(0) as u32;
-                self.write(address, val.to_le_bytes().to_vec());
+                //only the low width bits of the reg get stored, most significant byte first
+                let bytes = val.to_be_bytes()[8 - width / 8..].to_vec();
+
+                let address = self.effective_address(base.unwrap(), offset);
+                if !address.is_multiple_of((width / 8) as u64) {
+                    return Err(address_error(address, MemAccess::Store));
+                }
+                let address = self.swizzle(address, width / 8);
+
+                //SC/SCD only store if the link from LL/LLD is still intact,
+                //and report whether they did back into rt
+                if conditional {
+                    let link = self.cpu.borrow().rf.LLBit;
+                    if link {
+                        self.write(address, bytes)?;
+                    }
+                    if let crate::ir::GPRorCoPGPR::gpr(r) = src {
+                        self.cpu.borrow_mut().rf[r] = link as u64;
+                    }
+                } else {
+                    self.write(address, bytes)?;
+                }
             }
             Op::AluOp {
                 op_type,
@@ -198,22 +344,83 @@ impl System {
                 src_1,
                 src_2,
             } => {
+                //the HI/LO unit gets its own path since it doesnt writeback to a gpr the normal way
+                if matches!(
+                    op_type,
+                    MULT | MULTU
+                        | DIV
+                        | DIVU
+                        | DMULT
+                        | DMULTU
+                        | DDIV
+                        | DDIVU
+                        | MFHI
+                        | MFLO
+                        | MTHI
+                        | MTLO
+                ) {
+                    return self.execute_hi_lo(op_type, dst, src_1, src_2);
+                }
+
                 //get a closure to represent the actual operation
-                let function = match op_type {
-                    //ADD => |num1: u32, num2: u32| -> u32 {num1.wrapping_add(num2)},
-                    ORI => |num1: u64, num2: u64| -> u64 { num1 | num2 },
-                    ANDI => |num1: u64, num2: u64| -> u64 { num1 & num2 },
-                    SLL => |num1: u64, num2: u64| -> u64 { num1.shl(num2) },
-                    _ => unimplemented!(
-                        "PANIC: this alu opcode does not have an cloosure implemented for it yet"
-                    ),
+                //NOTE: all 32 bit ops have to sign extend their result into the full 64 bit reg
+                let function: fn(u64, u64) -> u64 = match op_type {
+                    //immediates come in zero extended, so the signed ones need to extend them here
+                    ADDI | ADDIU => |num1: u64, num2: u64| -> u64 {
+                        (num1 as u32).wrapping_add(num2 as u16 as i16 as u32) as i32 as u64
+                    },
+                    SLTI => |num1: u64, num2: u64| -> u64 {
+                        ((num1 as i64) < (num2 as u16 as i16 as i64)) as u64
+                    },
+                    SLTIU => |num1: u64, num2: u64| -> u64 {
+                        (num1 < (num2 as u16 as i16 as i64 as u64)) as u64
+                    },
+                    ANDI | AND => |num1: u64, num2: u64| -> u64 { num1 & num2 },
+                    ORI | OR => |num1: u64, num2: u64| -> u64 { num1 | num2 },
+                    XORI | XOR => |num1: u64, num2: u64| -> u64 { num1 ^ num2 },
+                    NOR => |num1: u64, num2: u64| -> u64 { !(num1 | num2) },
+                    LUI => |_num1: u64, num2: u64| -> u64 { (num2 << 16) as u32 as i32 as u64 },
+                    DADDI | DADDIU => |num1: u64, num2: u64| -> u64 {
+                        num1.wrapping_add(num2 as u16 as i16 as u64)
+                    },
+                    ADD | ADDU => |num1: u64, num2: u64| -> u64 {
+                        (num1 as u32).wrapping_add(num2 as u32) as i32 as u64
+                    },
+                    SUB | SUBU => |num1: u64, num2: u64| -> u64 {
+                        (num1 as u32).wrapping_sub(num2 as u32) as i32 as u64
+                    },
+                    SLT => |num1: u64, num2: u64| -> u64 { ((num1 as i64) < (num2 as i64)) as u64 },
+                    SLTU => |num1: u64, num2: u64| -> u64 { (num1 < num2) as u64 },
+                    DADD | DADDU => |num1: u64, num2: u64| -> u64 { num1.wrapping_add(num2) },
+                    DSUB | DSUBU => |num1: u64, num2: u64| -> u64 { num1.wrapping_sub(num2) },
+                    //shifts: num1 is always the value being shifted, num2 the amount
+                    SLL | SLLV => |num1: u64, num2: u64| -> u64 {
+                        (num1 as u32).shl(num2 & 0x1F) as i32 as u64
+                    },
+                    SRL | SRLV => |num1: u64, num2: u64| -> u64 {
+                        ((num1 as u32) >> (num2 & 0x1F)) as i32 as u64
+                    },
+                    //the vr4300 shifts the whole 64 bit reg before truncating for SRA
+                    SRA | SRAV => |num1: u64, num2: u64| -> u64 {
+                        ((num1 as i64) >> (num2 & 0x1F)) as i32 as u64
+                    },
+                    DSLL | DSLLV => |num1: u64, num2: u64| -> u64 { num1.shl(num2 & 0x3F) },
+                    DSRL | DSRLV => |num1: u64, num2: u64| -> u64 { num1 >> (num2 & 0x3F) },
+                    DSRA | DSRAV => {
+                        |num1: u64, num2: u64| -> u64 { ((num1 as i64) >> (num2 & 0x3F)) as u64 }
+                    }
+                    DSLL32 => |num1: u64, num2: u64| -> u64 { num1.shl((num2 & 0x1F) + 32) },
+                    DSRL32 => |num1: u64, num2: u64| -> u64 { num1 >> ((num2 & 0x1F) + 32) },
+                    DSRA32 => |num1: u64, num2: u64| -> u64 {
+                        ((num1 as i64) >> ((num2 & 0x1F) + 32)) as u64
+                    },
+                    MULT | MULTU | DIV | DIVU | DMULT | DMULTU | DDIV | DDIVU | MFHI | MFLO
+                    | MTHI | MTLO => unreachable!("HI/LO ops are handled above"),
                 };
                 //figure out where we are getting our values from
                 let a = match src_1 {
                     Some(r) => self.cpu.borrow().rf[r],
-                    None => panic!(
-                        "panicking executing an AluOP, no a src. Does this make sense in context?"
-                    ),
+                    None => 0,
                 };
 
                 let b = match src_2 {
@@ -221,6 +428,20 @@ impl System {
                     crate::ir::AluOpSrc::Reg(r) => self.cpu.borrow().rf[r],
                 };
 
+                //the trapping arithmetic ops raise Ov instead of wrapping, and dst is left alone
+                let overflowed = match op_type {
+                    ADD => (a as i32).checked_add(b as i32).is_none(),
+                    ADDI => (a as i32).checked_add(b as u16 as i16 as i32).is_none(),
+                    SUB => (a as i32).checked_sub(b as i32).is_none(),
+                    DADD => (a as i64).checked_add(b as i64).is_none(),
+                    DADDI => (a as i64).checked_add(b as u16 as i16 as i64).is_none(),
+                    DSUB => (a as i64).checked_sub(b as i64).is_none(),
+                    _ => false,
+                };
+                if overflowed {
+                    return Err(ExecutionError::Exception(Exception::IntegerOverflow));
+                }
+
                 //execute the closure for this operation
                 let result = function(a, b);
 
@@ -234,86 +455,816 @@ impl System {
                 likely,
                 link,
             } => {
-                if likely {
-                    unimplemented!("hit a likely control flow. need to add support for this");
-                }
-                if link {
-                    unimplemented!("hit a link control flow. need to add support for this");
-                }
-
                 let reg1 = match register {
                     Some(v) => match v {
-                        crate::ir::GPRorCoPGPR::cop => {
-                            unimplemented!("got a cop conditional in control flow")
-                        }
                         crate::ir::GPRorCoPGPR::gpr(r) => Some(r),
+                        //the cop branches test their condition line, not a register
+                        _ => unreachable!("control flow only reads gprs"),
                     },
                     None => None,
                 };
 
-                let take: bool = match conditional {
-                    ControlConditionalType::Ne { reg2 } => {
-                        let rf = &self.cpu.borrow_mut().rf;
-                        //self.cpu.borrow_mut().rf[reg2] != self.cpu.borrow_mut().rf[reg1.unwrap()]
-                        rf[reg1.unwrap()] != rf[reg2]
+                //the condition has to be read before we link, BLTZAL ra for example tests the old ra
+                let take = self.condition_met(conditional, reg1);
+
+                //work out the target before linking, JALR is allowed to link into its own rs
+                let target = match destination {
+                    //jumps stay within the 256MB region of their delay slot
+                    crate::ir::ControlDestType::Absolute { dest } => {
+                        (addr.wrapping_add(4) & !0x0FFF_FFFF) | dest as u64
+                    }
+                    crate::ir::ControlDestType::Relative { offset } => {
+                        addr.wrapping_add(4).wrapping_add_signed(offset)
+                    }
+                    crate::ir::ControlDestType::Register { r } => {
+                        let target = self.cpu.borrow().rf[r];
+                        trace!("control flow with a register going to {:#x}", target);
+                        target
                     }
-                    ControlConditionalType::Unconditional => true,
-                    _ => unimplemented!("hit a conditional condition we havent implemented yet"),
                 };
-                //let take = operation();
-                //drop(operation);
 
+                //the return address is written whether or not the branch is taken
+                //NOTE: PC is always kept as a sign extended 64 bit address, so this is too
+                if let Some(r) = link {
+                    self.cpu.borrow_mut().rf[r] = addr.wrapping_add(8);
+                }
+
+                let mut cpu = self.cpu.borrow_mut();
                 if take {
-                    match destination {
-                        crate::ir::ControlDestType::Absolute { dest } => {
-                            self.cpu.borrow_mut().rf.PC = dest as u64;
+                    cpu.branch(target);
+                } else if likely {
+                    cpu.nullify_delay_slot();
+                } else {
+                    cpu.branch_not_taken();
+                }
+
+                //unimplemented!("control flow opcodes not implemented yet");
+            }
+            Op::Move { width, src, dest } => {
+                let mut cpu = self.cpu.borrow_mut();
+
+                //the 32 bit moves only ever see the sign extended low word
+                let narrow = |val: u64| -> u64 {
+                    if width == 32 {
+                        val as u32 as i32 as u64
+                    } else {
+                        val
+                    }
+                };
+
+                let val = match src {
+                    crate::ir::GPRorCoPGPR::gpr(r) => cpu.rf[r],
+                    crate::ir::GPRorCoPGPR::cop0(reg) => cpu.read_cop0(reg),
+                    crate::ir::GPRorCoPGPR::cop1(r) if width == 32 => cpu.read_fpr_word(r) as u64,
+                    crate::ir::GPRorCoPGPR::cop1(r) => cpu.read_fpr_dword(r),
+                    crate::ir::GPRorCoPGPR::cop1_control(r) => cpu.read_fcr(r) as u64,
+                    crate::ir::GPRorCoPGPR::cop2_latch => cpu.cop2_latch,
+                };
+                let val = narrow(val);
+
+                match dest {
+                    crate::ir::GPRorCoPGPR::gpr(r) => cpu.rf[r] = val,
+                    crate::ir::GPRorCoPGPR::cop0(reg) => cpu.write_cop0(reg, val),
+                    crate::ir::GPRorCoPGPR::cop1(r) if width == 32 => {
+                        cpu.write_fpr_word(r, val as u32)
+                    }
+                    crate::ir::GPRorCoPGPR::cop1(r) => cpu.write_fpr_dword(r, val),
+                    crate::ir::GPRorCoPGPR::cop2_latch => cpu.cop2_latch = val,
+                    crate::ir::GPRorCoPGPR::cop1_control(r) => {
+                        cpu.write_fcr(r, val as u32);
+                        //CTC1 can set a cause bit whose enable is also set, that traps straight away
+                        let csr = cpu.rf.FCR31;
+                        if csr.Cause() & (csr.Enables() | fpu::UNIMPLEMENTED) != 0 {
+                            return Err(ExecutionError::Exception(Exception::FloatingPoint));
+                        }
+                    }
+                }
+            }
+            Op::FpuOp {
+                op_type,
+                fmt,
+                dst,
+                src_1,
+                src_2,
+            } => {
+                return self.execute_fpu(op_type, fmt, dst, src_1, src_2);
+            }
+            Op::System { opcode } => match opcode {
+                SystemOp::Eret => {
+                    let mut cpu = self.cpu.borrow_mut();
+                    //ERET has no delay slot, we go straight back
+                    if cpu.cop0.Status.ERL() {
+                        cpu.next_pc = cpu.cop0.ErrorEPC;
+                        cpu.cop0.Status.set_ERL(false);
+                    } else {
+                        cpu.next_pc = cpu.cop0.EPC;
+                        cpu.cop0.Status.set_EXL(false);
+                    }
+                    //returning from an exception always breaks an LL/SC sequence
+                    cpu.rf.LLBit = false;
+                }
+                SystemOp::Tlb { op } => {
+                    let mut cpu = self.cpu.borrow_mut();
+                    let is_64bit = cpu.is_64bit_addressing();
+                    match op {
+                        TlbOp::Probe => {
+                            //P (bit 31) flags a failed probe
+                            cpu.cop0.Index = match cpu.tlb.probe(cpu.cop0.EntryHi, is_64bit) {
+                                Some(i) => i as u32,
+                                None => 0x8000_0000 | (cpu.cop0.Index & 0x3F),
+                            };
                         }
-                        crate::ir::ControlDestType::Relative { offset } => {
-                            self.cpu.borrow_mut().rf.PC =
-                                self.cpu.borrow_mut().rf.PC.wrapping_add_signed(offset);
+                        TlbOp::Read => {
+                            let (page_mask, entry_hi, entry_lo0, entry_lo1) =
+                                cpu.tlb.read(cpu.cop0.Index as usize);
+                            cpu.cop0.PageMask = page_mask;
+                            cpu.cop0.EntryHi = entry_hi;
+                            cpu.cop0.EntryLo0 = entry_lo0;
+                            cpu.cop0.EntryLo1 = entry_lo1;
                         }
-                        crate::ir::ControlDestType::Register { r } => {
-                            self.cpu.borrow_mut().rf.PC = self.cpu.borrow_mut().rf[r];
-                            trace!(
-                                "control flow with a register going to {:#x}",
-                                self.cpu.borrow_mut().rf.PC
+                        TlbOp::WriteIndexed | TlbOp::WriteRandom => {
+                            let index = if matches!(op, TlbOp::WriteIndexed) {
+                                cpu.cop0.Index
+                            } else {
+                                cpu.cop0.Random
+                            };
+                            let (page_mask, entry_hi, entry_lo0, entry_lo1) = (
+                                cpu.cop0.PageMask,
+                                cpu.cop0.EntryHi,
+                                cpu.cop0.EntryLo0,
+                                cpu.cop0.EntryLo1,
+                            );
+                            cpu.tlb.write(
+                                index as usize,
+                                page_mask,
+                                entry_hi,
+                                entry_lo0,
+                                entry_lo1,
                             );
                         }
                     }
                 }
+                SystemOp::Cache { op, base, offset } => self.execute_cache(op, base, offset)?,
+                SystemOp::Syscall => {
+                    return Err(ExecutionError::Exception(Exception::Syscall));
+                }
+                SystemOp::Break { code } => {
+                    if let Some(hook) = self.break_hook.as_mut() {
+                        if hook(addr, code) {
+                            return Ok(1);
+                        }
+                    }
+                    return Err(ExecutionError::Exception(Exception::Breakpoint));
+                }
+                //there is nothing to order, every access here is already in program order
+                SystemOp::Sync => {}
+                SystemOp::Trap {
+                    condition,
+                    register,
+                } => {
+                    if self.condition_met(condition, Some(register)) {
+                        return Err(ExecutionError::Exception(Exception::Trap));
+                    }
+                }
+            },
+            Op::ReservedCop { .. } => {
+                return Err(ExecutionError::Exception(Exception::ReservedInstruction));
+            }
+            Op::MalformedOp => {
+                return Err(ExecutionError::Exception(Exception::ReservedInstruction));
+            }
+        }
 
-                //unimplemented!("control flow opcodes not implemented yet");
+        Ok(1)
+    }
+
+    //base + offset addressing used by every load and store
+    //the 16 bit offset is signed
+    fn effective_address(&self, base: GPR, offset: Option<u16>) -> u64 {
+        let base_addr = self.cpu.borrow().rf[base];
+        base_addr.wrapping_add(offset.unwrap_or(0) as i16 as i64 as u64)
+    }
+
+    //Status.RE makes user mode data accesses little endian. memory itself stays big endian,
+    //the cpu just flips the address within its doubleword instead, so a byte access at 0
+    //really hits byte 7, a halfword at 0 hits 6 and a word at 0 hits 4. doublewords are untouched
+    fn reverse_endian(&self) -> bool {
+        let cpu = self.cpu.borrow();
+        cpu.privilege_level() == 2 && cpu.cop0.Status.RE()
+    }
+
+    fn swizzle(&self, address: u64, len: usize) -> u64 {
+        if self.reverse_endian() {
+            address ^ (8 - len as u64)
+        } else {
+            address
+        }
+    }
+
+    //LWL/LWR/LDL/LDR
+    //only the bytes between the address and the edge of its word (or dword) are touched
+    //and get merged into the old contents of rt. all of this assumes a big endian bus
+    fn execute_unaligned_load(
+        &mut self,
+        width: usize,
+        left: bool,
+        dest: GPR,
+        base: GPR,
+        offset: Option<u16>,
+    ) -> Result<usize, ExecutionError> {
+        if self.reverse_endian() {
+            return self.execute_unaligned_load_reversed(width, left, dest, base, offset);
+        }
+
+        let address = self.effective_address(base, offset);
+        let size = (width / 8) as u64;
+        let byte_in_word = address & (size - 1);
+
+        //the left variants load from the address up to the end of the word,
+        //the right variants load from the start of the word up to and including the address
+        let (read_addr, len) = if left {
+            (address, size - byte_in_word)
+        } else {
+            (address & !(size - 1), byte_in_word + 1)
+        };
+        let bytes = self.read(read_addr, len as usize)?;
+        let data = bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+
+        let mut cpu = self.cpu.borrow_mut();
+        let old = cpu.rf[dest];
+        let loaded_bits = len * 8;
+        let result = match (width, left) {
+            //LWL: loaded bytes land in the top of the word
+            (32, true) => {
+                let shift = (4 - len) * 8;
+                let kept = if shift == 0 {
+                    0
+                } else {
+                    old & ((1 << shift) - 1)
+                };
+                ((data << shift) | kept) as u32 as i32 as u64
             }
-            Op::Move { src, dest } => {
-                unimplemented!("moce opcodes not implemented yet");
+            //LWR: loaded bytes land in the bottom of the word. the upper half of the reg is
+            //only replaced (by sign extension) when the whole word was loaded, otherwise
+            //it keeps whatever was there. in 32 bit mode that is already the sign extension
+            //of bit 31, which LWR leaves alone unless it loads it
+            (32, false) => {
+                if len == 4 {
+                    data as u32 as i32 as u64
+                } else {
+                    (old & !((1 << loaded_bits) - 1)) | data
+                }
             }
-            Op::System { opcode } => {
-                unimplemented!("System opcodes not implemented yet");
+            //LDL
+            (64, true) => {
+                let shift = (8 - len) * 8;
+                let kept = if shift == 0 {
+                    0
+                } else {
+                    old & ((1 << shift) - 1)
+                };
+                (data << shift) | kept
             }
-            Op::MalformedOp => {
-                panic!("malformed op in execution function!")
+            //LDR
+            (64, false) => {
+                if len == 8 {
+                    data
+                } else {
+                    (old & !((1 << loaded_bits) - 1)) | data
+                }
             }
+            _ => unreachable!("bad unaligned load width {}", width),
+        };
+        cpu.rf[dest] = result;
+
+        Ok(1)
+    }
+
+    //SWL/SWR/SDL/SDR
+    //mirror images of the loads. the left variants store the top of rt from the address up to
+    //the end of the word, the right variants store the bottom of rt from the start of the word
+    fn execute_unaligned_store(
+        &mut self,
+        width: usize,
+        left: bool,
+        src: GPR,
+        base: GPR,
+        offset: Option<u16>,
+    ) -> Result<usize, ExecutionError> {
+        if self.reverse_endian() {
+            return self.execute_unaligned_store_reversed(width, left, src, base, offset);
         }
 
-        Ok(0)
+        let address = self.effective_address(base, offset);
+        let size = width / 8;
+        let byte_in_word = (address as usize) & (size - 1);
+
+        let val = self.cpu.borrow().rf[src];
+        let reg_bytes = val.to_be_bytes();
+        let reg_bytes = &reg_bytes[8 - size..];
+
+        let (write_addr, bytes) = if left {
+            (address, reg_bytes[..size - byte_in_word].to_vec())
+        } else {
+            (
+                address & !(size as u64 - 1),
+                reg_bytes[size - byte_in_word - 1..].to_vec(),
+            )
+        };
+        self.write(write_addr, bytes)?;
+
+        Ok(1)
+    }
+
+    //the unaligned ops in reverse endian mode
+    //the address counts up from the least significant byte of the word now, so the left variants
+    //take the bytes from the start of the word up to the address and the right variants from the
+    //address to the end. every byte goes through the same swizzle as a plain byte access
+    fn execute_unaligned_load_reversed(
+        &mut self,
+        width: usize,
+        left: bool,
+        dest: GPR,
+        base: GPR,
+        offset: Option<u16>,
+    ) -> Result<usize, ExecutionError> {
+        let address = self.effective_address(base, offset);
+        let size = (width / 8) as u64;
+        let byte_in_word = address & (size - 1);
+        let word_addr = address & !(size - 1);
+
+        let lanes = if left {
+            0..byte_in_word + 1
+        } else {
+            byte_in_word..size
+        };
+        let mut data = 0u64;
+        for lane in lanes {
+            let byte = self.read((word_addr + lane) ^ 7, 1)?[0];
+            data |= (byte as u64) << (lane * 8);
+        }
+
+        let mut cpu = self.cpu.borrow_mut();
+        let old = cpu.rf[dest];
+        let result = if left {
+            //the bytes up to the address land in the top of the word
+            let shift = (size - 1 - byte_in_word) * 8;
+            let kept = if shift == 0 {
+                0
+            } else {
+                old & ((1 << shift) - 1)
+            };
+            let merged = (data << shift) | kept;
+            if width == 32 {
+                merged as u32 as i32 as u64
+            } else {
+                merged
+            }
+        } else {
+            //the bytes from the address land in the bottom, with the same rule for the upper
+            //half of the reg as the big endian LWR
+            let loaded_bits = (size - byte_in_word) * 8;
+            let merged = data >> (byte_in_word * 8);
+            if byte_in_word == 0 {
+                if width == 32 {
+                    merged as u32 as i32 as u64
+                } else {
+                    merged
+                }
+            } else {
+                (old & !((1 << loaded_bits) - 1)) | merged
+            }
+        };
+        cpu.rf[dest] = result;
+
+        Ok(1)
+    }
+
+    fn execute_unaligned_store_reversed(
+        &mut self,
+        width: usize,
+        left: bool,
+        src: GPR,
+        base: GPR,
+        offset: Option<u16>,
+    ) -> Result<usize, ExecutionError> {
+        let address = self.effective_address(base, offset);
+        let size = (width / 8) as u64;
+        let byte_in_word = address & (size - 1);
+        let word_addr = address & !(size - 1);
+
+        //lay rt out the way the word looks in memory, then store only the touched lanes
+        let val = self.cpu.borrow().rf[src];
+        let (data, lanes) = if left {
+            (val >> ((size - 1 - byte_in_word) * 8), 0..byte_in_word + 1)
+        } else {
+            (val << (byte_in_word * 8), byte_in_word..size)
+        };
+        for lane in lanes {
+            let byte = (data >> (lane * 8)) as u8;
+            self.write((word_addr + lane) ^ 7, vec![byte])?;
+        }
+
+        Ok(1)
+    }
+
+    //CACHE
+    //index ops address a line straight from the virtual address, hit ops translate it and only act
+    //on a line that actually holds that physical address
+    fn execute_cache(&mut self, op: CacheOp, base: GPR, offset: u16) -> Result<(), ExecutionError> {
+        let vaddr = self.effective_address(base, Some(offset));
+        let i_index = ICache::index(vaddr);
+        let d_index = DCache::index(vaddr);
+
+        match op {
+            CacheOp::IndexInvalidate { data: false } => {
+                self.cpu.borrow_mut().icache.lines[i_index].valid = false;
+            }
+            CacheOp::IndexInvalidate { data: true } => {
+                self.dcache_writeback(d_index);
+                self.cpu.borrow_mut().dcache.lines[d_index].valid = false;
+            }
+            //PState is valid in bit 1 and dirty in bit 0
+            CacheOp::IndexLoadTag { data } => {
+                let mut cpu = self.cpu.borrow_mut();
+                let (valid, dirty, tag) = if data {
+                    let line = &cpu.dcache.lines[d_index];
+                    (line.valid, line.dirty, line.tag)
+                } else {
+                    let line = &cpu.icache.lines[i_index];
+                    (line.valid, false, line.tag)
+                };
+                cpu.cop0.TagLo.set_PTagLo(tag);
+                cpu.cop0.TagLo.set_PState((valid as u8) << 1 | dirty as u8);
+            }
+            CacheOp::IndexStoreTag { data } => {
+                let mut cpu = self.cpu.borrow_mut();
+                let tag = cpu.cop0.TagLo.PTagLo();
+                let state = cpu.cop0.TagLo.PState();
+                if data {
+                    let line = &mut cpu.dcache.lines[d_index];
+                    line.tag = tag;
+                    line.valid = state & 0b10 != 0;
+                    line.dirty = state & 0b01 != 0;
+                } else {
+                    let line = &mut cpu.icache.lines[i_index];
+                    line.tag = tag;
+                    line.valid = state & 0b10 != 0;
+                }
+            }
+            CacheOp::CreateDirtyExclusive => {
+                let (phys, _) = self.translate(vaddr, MemAccess::Store)?;
+                let hit = self.cpu.borrow().dcache.hit(vaddr, phys);
+                if !hit {
+                    self.dcache_writeback(d_index);
+                }
+                //the line is claimed without reading memory, its old data just stays there
+                let mut cpu = self.cpu.borrow_mut();
+                let line = &mut cpu.dcache.lines[d_index];
+                line.tag = phys >> 12;
+                line.valid = true;
+                line.dirty = true;
+            }
+            CacheOp::HitInvalidate { data } => {
+                let (phys, _) = self.translate(vaddr, MemAccess::Load)?;
+                let mut cpu = self.cpu.borrow_mut();
+                if data && cpu.dcache.hit(vaddr, phys) {
+                    cpu.dcache.lines[d_index].valid = false;
+                } else if !data && cpu.icache.hit(vaddr, phys) {
+                    cpu.icache.lines[i_index].valid = false;
+                }
+            }
+            CacheOp::HitWriteBackInvalidate => {
+                let (phys, _) = self.translate(vaddr, MemAccess::Load)?;
+                if self.cpu.borrow().dcache.hit(vaddr, phys) {
+                    self.dcache_writeback(d_index);
+                    self.cpu.borrow_mut().dcache.lines[d_index].valid = false;
+                }
+            }
+            CacheOp::HitWriteBack { data: true } => {
+                let (phys, _) = self.translate(vaddr, MemAccess::Load)?;
+                if self.cpu.borrow().dcache.hit(vaddr, phys) {
+                    self.dcache_writeback(d_index);
+                }
+            }
+            //the instruction cache has no dirty bit, a hit line is always written out
+            CacheOp::HitWriteBack { data: false } => {
+                let (phys, _) = self.translate(vaddr, MemAccess::Load)?;
+                let line = {
+                    let cpu = self.cpu.borrow();
+                    if !cpu.icache.hit(vaddr, phys) {
+                        return Ok(());
+                    }
+                    (
+                        cpu.icache.line_addr(i_index),
+                        cpu.icache.lines[i_index].data,
+                    )
+                };
+                self.write_phys(line.0, line.1.to_vec());
+            }
+            CacheOp::Fill => {
+                let (phys, _) = self.translate(vaddr, MemAccess::Fetch)?;
+                self.icache_fill(i_index, phys);
+            }
+            CacheOp::Undefined => {}
+        }
+
+        Ok(())
+    }
+
+    //evaluates a branch or trap condition against the current register file
+    //reg1 is the rs operand, which unconditional control flow doesnt have
+    fn condition_met(&self, conditional: ControlConditionalType, reg1: Option<GPR>) -> bool {
+        let rf = &self.cpu.borrow().rf;
+        let rs = match reg1 {
+            Some(r) => rf[r],
+            None => 0,
+        };
+
+        match conditional {
+            ControlConditionalType::Unconditional => true,
+            ControlConditionalType::Eq { reg2 } => rs == rf[reg2],
+            ControlConditionalType::Ne { reg2 } => rs != rf[reg2],
+            ControlConditionalType::Ge { reg2 } => (rs as i64) >= (rf[reg2] as i64),
+            ControlConditionalType::GeU { reg2 } => rs >= rf[reg2],
+            ControlConditionalType::Lt { reg2 } => (rs as i64) < (rf[reg2] as i64),
+            ControlConditionalType::LtU { reg2 } => rs < rf[reg2],
+            //the trap immediates are sign extended even for the unsigned compares
+            ControlConditionalType::GeI { imm } => (rs as i64) >= (imm as i16 as i64),
+            ControlConditionalType::GeIU { imm } => rs >= (imm as i16 as i64 as u64),
+            ControlConditionalType::LtI { imm } => (rs as i64) < (imm as i16 as i64),
+            ControlConditionalType::LtIU { imm } => rs < (imm as i16 as i64 as u64),
+            ControlConditionalType::EqI { imm } => rs == (imm as i16 as i64 as u64),
+            ControlConditionalType::NeI { imm } => rs != (imm as i16 as i64 as u64),
+            ControlConditionalType::GEZ => (rs as i64) >= 0,
+            ControlConditionalType::GTZ => (rs as i64) > 0,
+            ControlConditionalType::LEZ => (rs as i64) <= 0,
+            ControlConditionalType::LTZ => (rs as i64) < 0,
+            //BC1F/BC1T test the compare bit in FCR31
+            ControlConditionalType::CopZFalse { cop: 1 } => !self.cpu.borrow().rf.FCR31.C(),
+            ControlConditionalType::CopZTrue { cop: 1 } => self.cpu.borrow().rf.FCR31.C(),
+            ControlConditionalType::CopZFalse { .. } | ControlConditionalType::CopZTrue { .. } => {
+                //BC0/BC2/BC3 are lifted as ReservedCop and never get here
+                unreachable!("only cop1 has a condition line to branch on")
+            }
+        }
+    }
+
+    //cop1 arithmetic, compares and conversions
+    //everything rounds according to FCR31.RM, see fpu.rs. Cause is rebuilt by every op, and if any
+    //enabled cause bit (or E, which cant be masked) comes up we trap without writing fd or Flags
+    fn execute_fpu(
+        &mut self,
+        op_type: FpuOps,
+        fmt: FpuFormat,
+        fd: u8,
+        fs: u8,
+        ft: u8,
+    ) -> Result<usize, ExecutionError> {
+        let mut cpu = self.cpu.borrow_mut();
+        let csr = cpu.rf.FCR31;
+        let cycles = op_type.cycles(&fmt);
+
+        let (s_a, s_b) = (
+            f32::from_bits(cpu.read_fpr_word(fs)),
+            f32::from_bits(cpu.read_fpr_word(ft)),
+        );
+        let (d_a, d_b) = (
+            f64::from_bits(cpu.read_fpr_dword(fs)),
+            f64::from_bits(cpu.read_fpr_dword(ft)),
+        );
+        let (w_a, l_a) = (
+            cpu.read_fpr_word(fs) as i32 as i64,
+            cpu.read_fpr_dword(fs) as i64,
+        );
+
+        //the rounding for the fixed point conversions, None for everything else
+        let to_int_rm = match op_type {
+            FpuOps::CVT { .. } => Some(RoundingMode::from(csr.RM())),
+            FpuOps::ROUND { .. } => Some(RoundingMode::Nearest),
+            FpuOps::TRUNC { .. } => Some(RoundingMode::Zero),
+            FpuOps::CEIL { .. } => Some(RoundingMode::PlusInf),
+            FpuOps::FLOOR { .. } => Some(RoundingMode::MinusInf),
+            _ => None,
+        };
+
+        //(result bits, whether its a doubleword, cause)
+        let (result, double, cause) = match (&op_type, &fmt) {
+            //MOV is just a copy, it doesnt touch the status at all
+            (FpuOps::MOV, FpuFormat::S) => {
+                let val = cpu.read_fpr_word(fs);
+                cpu.write_fpr_word(fd, val);
+                return Ok(cycles);
+            }
+            (FpuOps::MOV, FpuFormat::D) => {
+                let val = cpu.read_fpr_dword(fs);
+                cpu.write_fpr_dword(fd, val);
+                return Ok(cycles);
+            }
+            (FpuOps::C { cond }, FpuFormat::S | FpuFormat::D) => {
+                let (c, cause) = if matches!(fmt, FpuFormat::S) {
+                    fpu::compare(s_a, s_b, *cond)
+                } else {
+                    fpu::compare(d_a, d_b, *cond)
+                };
+                cpu.rf.FCR31.set_Cause(cause);
+                if cause & csr.Enables() != 0 {
+                    return Err(ExecutionError::Exception(Exception::FloatingPoint));
+                }
+                cpu.rf.FCR31.set_Flags(csr.Flags() | cause);
+                cpu.rf.FCR31.set_C(c);
+                return Ok(cycles);
+            }
+            (
+                FpuOps::ADD
+                | FpuOps::SUB
+                | FpuOps::MUL
+                | FpuOps::DIV
+                | FpuOps::SQRT
+                | FpuOps::ABS
+                | FpuOps::NEG,
+                FpuFormat::S,
+            ) => {
+                let (r, cause) = fpu::arith(&op_type, s_a, s_b, csr);
+                (r.to_bits() as u64, false, cause)
+            }
+            (
+                FpuOps::ADD
+                | FpuOps::SUB
+                | FpuOps::MUL
+                | FpuOps::DIV
+                | FpuOps::SQRT
+                | FpuOps::ABS
+                | FpuOps::NEG,
+                FpuFormat::D,
+            ) => {
+                let (r, cause) = fpu::arith(&op_type, d_a, d_b, csr);
+                (r.to_bits(), true, cause)
+            }
+            (FpuOps::CVT { to: FpuFormat::S }, FpuFormat::D) => {
+                let (r, cause) = fpu::to_single(d_a, csr);
+                (r.to_bits() as u64, false, cause)
+            }
+            (FpuOps::CVT { to: FpuFormat::D }, FpuFormat::S) => {
+                let (r, cause) = fpu::to_double(s_a);
+                (r.to_bits(), true, cause)
+            }
+            (FpuOps::CVT { to }, FpuFormat::W | FpuFormat::L)
+                if matches!(to, FpuFormat::S | FpuFormat::D) =>
+            {
+                let v = if matches!(fmt, FpuFormat::W) {
+                    w_a
+                } else {
+                    l_a
+                };
+                if matches!(to, FpuFormat::S) {
+                    let (r, cause) = fpu::from_int::<f32>(v, csr);
+                    (r.to_bits() as u64, false, cause)
+                } else {
+                    let (r, cause) = fpu::from_int::<f64>(v, csr);
+                    (r.to_bits(), true, cause)
+                }
+            }
+            (
+                FpuOps::CVT { to }
+                | FpuOps::ROUND { to }
+                | FpuOps::TRUNC { to }
+                | FpuOps::CEIL { to }
+                | FpuOps::FLOOR { to },
+                FpuFormat::S | FpuFormat::D,
+            ) if matches!(to, FpuFormat::W | FpuFormat::L) => {
+                let long = matches!(to, FpuFormat::L);
+                let (r, cause) = if matches!(fmt, FpuFormat::S) {
+                    fpu::to_int(s_a, to_int_rm.unwrap(), long)
+                } else {
+                    fpu::to_int(d_a, to_int_rm.unwrap(), long)
+                };
+                if long {
+                    (r as u64, true, cause)
+                } else {
+                    (r as u32 as u64, false, cause)
+                }
+            }
+            //everything else, like arithmetic on W/L or CVT.S.S, is a reserved combination
+            _ => (0, false, fpu::UNIMPLEMENTED),
+        };
+
+        cpu.rf.FCR31.set_Cause(cause);
+        if cause & (csr.Enables() | fpu::UNIMPLEMENTED) != 0 {
+            return Err(ExecutionError::Exception(Exception::FloatingPoint));
+        }
+        cpu.rf.FCR31.set_Flags(csr.Flags() | cause);
+
+        if double {
+            cpu.write_fpr_dword(fd, result);
+        } else {
+            cpu.write_fpr_word(fd, result as u32);
+        }
+
+        Ok(cycles)
+    }
+
+    //runs the multiply/divide unit and the HI/LO moves
+    //NOTE: 32 bit results are sign extended into HI and LO just like gpr results
+    //returns how many cycles the op keeps the unit busy for
+    fn execute_hi_lo(
+        &mut self,
+        op_type: AluOps,
+        dst: GPR,
+        src_1: Option<GPR>,
+        src_2: AluOpSrc,
+    ) -> Result<usize, ExecutionError> {
+        let mut cpu = self.cpu.borrow_mut();
+
+        let a = match src_1 {
+            Some(r) => cpu.rf[r],
+            None => 0,
+        };
+        let b = match src_2 {
+            AluOpSrc::Imm(v) => v as u64,
+            AluOpSrc::Reg(r) => cpu.rf[r],
+        };
+
+        match op_type {
+            MULT => {
+                let result = (a as i32 as i64).wrapping_mul(b as i32 as i64);
+                cpu.rf.LO = result as i32 as u64;
+                cpu.rf.HI = (result >> 32) as i32 as u64;
+            }
+            MULTU => {
+                let result = (a as u32 as u64) * (b as u32 as u64);
+                cpu.rf.LO = result as u32 as i32 as u64;
+                cpu.rf.HI = (result >> 32) as u32 as i32 as u64;
+            }
+            //dividing by zero doesnt trap, LO gets -1 or 1 depending on the sign of the
+            //dividend (all ones for the unsigned versions) and HI gets the dividend back.
+            //INT_MIN / -1 wraps back around to INT_MIN with a remainder of 0
+            DIV => {
+                let (n, d) = (a as i32, b as i32);
+                if d == 0 {
+                    cpu.rf.LO = if n >= 0 { u64::MAX } else { 1 };
+                    cpu.rf.HI = n as u64;
+                } else {
+                    cpu.rf.LO = n.wrapping_div(d) as u64;
+                    cpu.rf.HI = n.wrapping_rem(d) as u64;
+                }
+            }
+            DIVU => {
+                let (n, d) = (a as u32, b as u32);
+                cpu.rf.LO = n.checked_div(d).map_or(u64::MAX, |q| q as i32 as u64);
+                cpu.rf.HI = n.checked_rem(d).unwrap_or(n) as i32 as u64;
+            }
+            DMULT => {
+                let result = (a as i64 as i128) * (b as i64 as i128);
+                cpu.rf.LO = result as u64;
+                cpu.rf.HI = (result >> 64) as u64;
+            }
+            DMULTU => {
+                let result = (a as u128) * (b as u128);
+                cpu.rf.LO = result as u64;
+                cpu.rf.HI = (result >> 64) as u64;
+            }
+            DDIV => {
+                let (n, d) = (a as i64, b as i64);
+                if d == 0 {
+                    cpu.rf.LO = if n >= 0 { u64::MAX } else { 1 };
+                    cpu.rf.HI = n as u64;
+                } else {
+                    cpu.rf.LO = n.wrapping_div(d) as u64;
+                    cpu.rf.HI = n.wrapping_rem(d) as u64;
+                }
+            }
+            DDIVU => {
+                cpu.rf.LO = a.checked_div(b).unwrap_or(u64::MAX);
+                cpu.rf.HI = a.checked_rem(b).unwrap_or(a);
+            }
+            MFHI => cpu.rf[dst] = cpu.rf.HI,
+            MFLO => cpu.rf[dst] = cpu.rf.LO,
+            MTHI => cpu.rf.HI = a,
+            MTLO => cpu.rf.LO = a,
+            _ => unreachable!("{:?} is not a HI/LO op", op_type),
+        }
+
+        Ok(op_type.cycles())
     }
 
     //returns a block of addresses and the opcodes at those addresses
-    pub fn find_next_basic_block(&self) -> Vec<(u64, u32)> {
+    //if the very first fetch faults that exception comes back as an Err. a fault any further in just
+    //ends the block early, it gets raised for real once execution reaches that address
+    pub fn find_next_basic_block(&self) -> Result<Vec<(u64, u32)>, ExecutionError> {
         //uhhhh. search forward from pc until we see a branch instruction!
         //dont forget to always include that pesky delay slot!
 
         let mut base_pc = self.cpu.borrow().rf.PC;
 
-        //TODO: this might need to become u64s if we are for some reason running in 64 bit mode
         let mut block_vec: Vec<(u64, u32)> = Vec::new();
 
         loop {
-            let next_instr = self.read(base_pc.try_into().unwrap(), 4).unwrap();
-            let next_instr = (next_instr[0] as u32) << 24
-                | (next_instr[1] as u32) << 16
-                | (next_instr[2] as u32) << 8
-                | next_instr[3] as u32;
+            let next_instr = match self.fetch(base_pc) {
+                Ok(instr) => instr,
+                Err(e) if block_vec.is_empty() => return Err(e),
+                Err(_) => break,
+            };
 
             if BRANCH_OR_JUMP_OPS.contains(&(((next_instr & 0xFC00_0000) >> 26) as u8))
                 || ((next_instr & 0xFC00_0000) >> 26 == 0
@@ -326,12 +1277,10 @@ impl System {
                 base_pc += 4;
 
                 //PUSH THE FUCKING DELAY SLOT INSTRUCTION HERE
-                let next_instr = self.read(base_pc.try_into().unwrap(), 4).unwrap();
-                let next_instr = (next_instr[0] as u32) << 24
-                    | (next_instr[1] as u32) << 16
-                    | (next_instr[2] as u32) << 8
-                    | next_instr[3] as u32;
-                block_vec.push((base_pc, next_instr));
+                //NOTE: this goes in even for branch likelies, execution decides whether it runs
+                if let Ok(next_instr) = self.fetch(base_pc) {
+                    block_vec.push((base_pc, next_instr));
+                }
                 break;
             } else {
                 block_vec.push((base_pc, next_instr));
@@ -340,7 +1289,20 @@ impl System {
             base_pc += 4;
         }
 
-        block_vec
+        Ok(block_vec)
+    }
+
+    //reads one big endian instruction word
+    fn fetch(&self, addr: u64) -> Result<u32, ExecutionError> {
+        //a jump to a misaligned address faults on the fetch, with BadVAddr and EPC both pointing at it
+        if addr & 3 != 0 {
+            return Err(address_error(addr, MemAccess::Fetch));
+        }
+        let bytes = self.read_as(addr, 4, MemAccess::Fetch)?;
+        Ok((bytes[0] as u32) << 24
+            | (bytes[1] as u32) << 16
+            | (bytes[2] as u32) << 8
+            | bytes[3] as u32)
     }
 
     pub fn new() -> Self {
@@ -353,6 +1315,7 @@ impl System {
         let cpu = Rc::new(RefCell::new(Cpu::new(ram.clone(), cart.clone())));
         let rcp = Rc::new(RefCell::new(Rcp::new()));
         let pi = Rc::new(RefCell::new(PI::default()));
+        let mi = Rc::new(RefCell::new(MI::default()));
         let rdram = Rc::new(RefCell::new(Rdram::default()));
 
         //construct the actuall system
@@ -361,7 +1324,9 @@ impl System {
             cart,
             rcp,
             pi,
+            mi,
             rdram,
+            break_hook: None,
         }
     }
 
@@ -407,7 +1372,8 @@ impl System {
 
         //jumping to ipl3 (this is where we will start actually executing instead of just fuzzing the same effects)
         //base of dmem is 0x04000000(phys) which ASSUMING we are kseg1, is then a virtual address of 0x04000000 + 0xA0000000 = 0xA4000000
-        self.cpu.borrow_mut().rf.PC = 0xA4000040;
+        //sign extended since thats what a 32 bit address looks like in a 64 bit reg
+        self.cpu.borrow_mut().set_pc(0xFFFF_FFFF_A400_0040);
     }
 
     ///////////////////////////////////////////////////////////////////////////////////////////////
@@ -415,9 +1381,41 @@ impl System {
     //lets assume that we are always passing virtual addresses here, and then we can handle all mmu
     //translations here and return or write data to physical addresses
     //length is in bytes
-    pub fn read(&self, addr: u32, len: usize) -> Result<Vec<u8>, String> {
-        let phys = self.virt_to_phys(addr);
+    pub fn read(&self, addr: u64, len: usize) -> Result<Vec<u8>, ExecutionError> {
+        self.read_as(addr, len, MemAccess::Load)
+    }
 
+    //fetches go through the icache, loads through the dcache
+    //an aligned access never crosses a line so we only ever have to look at one
+    fn read_as(
+        &self,
+        vaddr: u64,
+        len: usize,
+        access: MemAccess,
+    ) -> Result<Vec<u8>, ExecutionError> {
+        let (phys, cached) = self.translate(vaddr, access)?;
+        self.check_watch(phys, access)?;
+        check_bus(phys, access)?;
+        if !cached {
+            return Ok(self.read_phys(phys, len));
+        }
+
+        if access == MemAccess::Fetch {
+            let index = ICache::index(vaddr);
+            if !self.cpu.borrow().icache.hit(vaddr, phys) {
+                self.icache_fill(index, phys);
+            }
+            let offset = phys as usize % ICACHE_LINE;
+            Ok(self.cpu.borrow().icache.lines[index].data[offset..offset + len].to_vec())
+        } else {
+            let index = self.dcache_line(vaddr, phys);
+            let offset = phys as usize % DCACHE_LINE;
+            Ok(self.cpu.borrow().dcache.lines[index].data[offset..offset + len].to_vec())
+        }
+    }
+
+    //straight to the bus, no caches
+    fn read_phys(&self, phys: u32, len: usize) -> Vec<u8> {
         /*trace!(
             "in system::read, converted virt {:#x} to phys {:#x}",
             addr,
@@ -426,7 +1424,7 @@ impl System {
 
         match phys {
             //RDRAM
-            0x0000_0000..=0x03FFFFFF => self.rdram.borrow_mut().read(addr, len),
+            0x0000_0000..=0x03FFFFFF => self.rdram.borrow_mut().read(phys, len).unwrap(),
 
             //0x04000000 	0x04000FFF 	RSP DMEM 	RSP Data Memory
             //0x04001000 	0x04001FFF 	RSP IMEM 	RSP Instruction Memory
@@ -434,21 +1432,35 @@ impl System {
             0x04000000..=0x0403FFFF => {
                 let sub_address = (phys - 0x04000000) % 8192;
                 match sub_address {
-                    0x0..=0xFFF => {
-                        return Ok(self.rcp.borrow().rsp.borrow().DMEM
-                            [sub_address as usize..=sub_address as usize + len]
-                            .to_vec());
-                    } //DMEM
+                    0x0..=0xFFF => self.rcp.borrow().rsp.borrow().DMEM
+                        [sub_address as usize..sub_address as usize + len]
+                        .to_vec(), //DMEM
                     0x1000..=0x1FFF => {
-                        return Ok(self.rcp.borrow().rsp.borrow().IMEM
-                            [sub_address as usize..=sub_address as usize + len]
-                            .to_vec());
+                        let sub_address = sub_address - 0x1000;
+                        self.rcp.borrow().rsp.borrow().IMEM
+                            [sub_address as usize..sub_address as usize + len]
+                            .to_vec()
                     } //IMEM
                     _ => unreachable!("calculated an impossible imem or dmem addr"),
                 }
             }
-            0x04600000..=0x046FFFFF => {
-                return self.pi.borrow().read(phys, len);
+            0x04300000..=0x043FFFFF => self.mi.borrow().read(phys, len).unwrap(),
+            0x04600000..=0x046FFFFF => self.pi.borrow().read(phys, len).unwrap(),
+            //RI
+            0x04700000..=0x047FFFFF => self.rdram.borrow().read(phys, len).unwrap(),
+            //PI open bus, only reachable through xkphys or the tlb
+            //every word reads back the low half of its own address, twice
+            0x2000_0000..=0x7FFF_FFFF => (phys..phys + len as u32)
+                .map(|byte_addr| {
+                    let half = (byte_addr & !3) & 0xFFFF;
+                    ((half << 16) | half).to_be_bytes()[(byte_addr & 3) as usize]
+                })
+                .collect(),
+            //cpu accesses up here are turned into bus errors by check_bus, so only a cache line
+            //with a tag pointing up here can get this far
+            0x8000_0000..=0xFFFF_FFFF => {
+                log::error!("read from {phys:#x}, which the rcp ignores. real hardware hangs here");
+                vec![0; len]
             }
             _ => {
                 panic!(
@@ -458,10 +1470,96 @@ impl System {
             }
         }
     }
-    pub fn write(&mut self, addr: u32, val: Vec<u8>) {
-        let phys = self.virt_to_phys(addr);
+    pub fn write(&mut self, vaddr: u64, val: Vec<u8>) -> Result<(), ExecutionError> {
+        let (phys, cached) = self.translate(vaddr, MemAccess::Store)?;
+        self.check_watch(phys, MemAccess::Store)?;
+        check_bus(phys, MemAccess::Store)?;
+        if !cached {
+            self.write_phys(phys, val);
+            return Ok(());
+        }
 
+        //the dcache is write back and allocates on a store miss
+        let index = self.dcache_line(vaddr, phys);
+        let offset = phys as usize % DCACHE_LINE;
+        let mut cpu = self.cpu.borrow_mut();
+        let line = &mut cpu.dcache.lines[index];
+        line.data[offset..offset + val.len()].copy_from_slice(&val);
+        line.dirty = true;
+
+        Ok(())
+    }
+
+    //makes sure the dcache line for vaddr holds phys, writing back whatever it evicts
+    //returns the line index
+    fn dcache_line(&self, vaddr: u64, phys: u32) -> usize {
+        let index = DCache::index(vaddr);
+        if self.cpu.borrow().dcache.hit(vaddr, phys) {
+            return index;
+        }
+
+        self.dcache_writeback(index);
+        let data = self.read_phys(phys & !(DCACHE_LINE as u32 - 1), DCACHE_LINE);
+        let mut cpu = self.cpu.borrow_mut();
+        let line = &mut cpu.dcache.lines[index];
+        line.data.copy_from_slice(&data);
+        line.tag = phys >> 12;
+        line.valid = true;
+        line.dirty = false;
+
+        index
+    }
+
+    //writes a valid dirty dcache line out to memory and marks it clean
+    fn dcache_writeback(&self, index: usize) {
+        let (addr, data) = {
+            let mut cpu = self.cpu.borrow_mut();
+            let addr = cpu.dcache.line_addr(index);
+            let line = &mut cpu.dcache.lines[index];
+            if !(line.valid && line.dirty) {
+                return;
+            }
+            line.dirty = false;
+            (addr, line.data)
+        };
+        self.write_phys(addr, data.to_vec());
+    }
+
+    //loads a whole icache line from memory
+    fn icache_fill(&self, index: usize, phys: u32) {
+        let data = self.read_phys(phys & !(ICACHE_LINE as u32 - 1), ICACHE_LINE);
+        let mut cpu = self.cpu.borrow_mut();
+        let line = &mut cpu.icache.lines[index];
+        line.data.copy_from_slice(&data);
+        line.tag = phys >> 12;
+        line.valid = true;
+    }
+
+    //straight to the bus, no caches
+    fn write_phys(&self, phys: u32, val: Vec<u8>) {
         match phys {
+            //RDRAM
+            0x0000_0000..=0x03FFFFFF => {
+                self.rdram.borrow_mut().write(phys, val).unwrap();
+            }
+
+            //RSP DMEM/IMEM and their mirrors
+            0x04000000..=0x0403FFFF => {
+                let sub_address = ((phys - 0x04000000) % 8192) as usize;
+                let rsp = self.rcp.borrow().rsp.clone();
+                let mut rsp = rsp.borrow_mut();
+                match sub_address {
+                    0x0..=0xFFF => {
+                        rsp.DMEM[sub_address..sub_address + val.len()].copy_from_slice(&val);
+                    } //DMEM
+                    0x1000..=0x1FFF => {
+                        let sub_address = sub_address - 0x1000;
+                        rsp.IMEM[sub_address..sub_address + val.len()].copy_from_slice(&val);
+                    } //IMEM
+                    _ => unreachable!("calculated an impossible imem or dmem addr"),
+                }
+            }
+
             //RCP PI address space NOT EXTERNAL BUS
             0x04600000..=0x046FFFFF => {
                 let possible_dma = self.pi.borrow_mut().write(phys, val);
@@ -504,41 +1602,152 @@ impl System {
                         }
                     }
 
+                    self.pi.borrow_mut().dma_complete();
                     debug!("end PI DMA");
                 }
+
+                //a dma finishing or PI_STATUS acknowledging it changes the PI's line into the MI
+                let pending = self.pi.borrow().interrupt();
+                self.mi.borrow_mut().set_interrupt(MI_INTR_PI, pending);
+                self.update_rcp_interrupt();
+            }
+            //MI
+            0x04300000..=0x043FFFFF => {
+                self.mi.borrow_mut().write(phys, val);
+                self.update_rcp_interrupt();
+            }
+            //RI
+            0x04700000..=0x047FFFFF => {
+                self.rdram.borrow_mut().write(phys, val).unwrap();
+            }
+            //PI open bus, writes go nowhere
+            0x2000_0000..=0x7FFF_FFFF => {}
+            0x8000_0000..=0xFFFF_FFFF => {
+                log::error!("write to {phys:#x}, which the rcp ignores. real hardware hangs here")
             }
             _ => panic!("trying to write to a physical address we havent mapped yet: {phys:#x}"),
         }
     }
 
-    pub fn virt_to_phys(&self, virt: u32) -> u32 {
-        match virt {
-            0x0000_0000..=0x7FFF_FFFF => {
-                panic!("tried to convert a virtual address in KUSEG {virt:#x}")
-            } //KUSEG
-            0x8000_0000..=0x9FFF_FFFF => {
-                panic!("tried to convert a virtual address in KSEG0 {virt:#x}")
-            } //KSEG0
-            0xA000_0000..=0xBFFF_FFFF => {
-                //trace!("in system::virt_to_phys, virt is {:#x}", virt);
-
-                let conversion = virt.checked_sub(0xA000_0000);
-
-                //trace!("after sub: {:?}", conversion);
-                match conversion {
-                    Some(v) => {
-                        //trace!("value is {:#x}", v);
-                        return v;
-                    }
-                    None => panic!("error converting address in KSEG1 {virt:#x}"),
-                }
-            } //KSEG1
-            0xC000_0000..=0xDFFF_FFFF => {
-                panic!("tried to convert a virtual address in KSEG2 {virt:#x}")
-            } //KSEG2
-            0xE000_0000..=0xFFFF_FFFF => {
-                panic!("tried to convert a virtual address in KSEG3 {virt:#x}")
-            } //KSEG3
+    //the MI is the only thing wired to the cpus IP2, everything in the rcp goes through its mask
+    fn update_rcp_interrupt(&self) {
+        let pending = self.mi.borrow().interrupt();
+        self.cpu.borrow_mut().set_interrupt_line(2, pending);
+    }
+
+    //WatchLo/WatchHi hold bits 35..3 of a physical address, so a watchpoint covers a whole
+    //doubleword. R (bit 1) traps loads and W (bit 0) traps stores before they touch memory
+    //fetches and cache ops never trigger it, and it is ignored while EXL is set
+    fn check_watch(&self, phys: u32, access: MemAccess) -> Result<(), ExecutionError> {
+        let cpu = self.cpu.borrow();
+        let enabled = match access {
+            MemAccess::Fetch => false,
+            MemAccess::Load => cpu.cop0.WatchLo & 0b10 != 0,
+            MemAccess::Store => cpu.cop0.WatchLo & 0b01 != 0,
+        };
+        if !enabled || cpu.cop0.Status.EXL() {
+            return Ok(());
+        }
+
+        let watch_addr = ((cpu.cop0.WatchHi as u64) << 32) | (cpu.cop0.WatchLo & !0b111) as u64;
+        if watch_addr == (phys & !0b111) as u64 {
+            return Err(ExecutionError::Exception(Exception::Watch));
+        }
+        Ok(())
+    }
+
+    pub fn virt_to_phys(&self, vaddr: u64, access: MemAccess) -> Result<u32, ExecutionError> {
+        self.translate(vaddr, access).map(|(phys, _)| phys)
+    }
+
+    //returns the physical address and whether the access goes through the caches
+    //in 32 bit mode only sign extended addresses are legal, so the 32 bit segments all show up as the
+    //compatibility segments at the top of the 64 bit map
+    //user mode only gets (X)KUSEG, supervisor mode adds (X)KSSEG, everything else is kernel only
+    fn translate(&self, vaddr: u64, access: MemAccess) -> Result<(u32, bool), ExecutionError> {
+        let (is_64bit, erl, k0, level) = {
+            let cpu = self.cpu.borrow();
+            (
+                cpu.is_64bit_addressing(),
+                cpu.cop0.Status.ERL(),
+                cpu.cop0.Config.K0(),
+                cpu.privilege_level(),
+            )
+        };
+        let address_error = || Err(address_error(vaddr, access));
+
+        if !is_64bit && vaddr as i32 as u64 != vaddr {
+            return address_error();
+        }
+
+        match vaddr {
+            //XKUSEG, KUSEG in 32 bit mode
+            0x0000_0000_0000_0000..=0x0000_00FF_FFFF_FFFF => {
+                //with ERL set the bottom 2GB turns into an unmapped, uncached window onto physical memory
+                if erl && vaddr < 0x8000_0000 {
+                    return Ok((vaddr as u32, false));
+                }
+                self.tlb_translate(vaddr, access)
+            }
+            //XKSSEG
+            0x4000_0000_0000_0000..=0x4000_00FF_FFFF_FFFF if level <= 1 => {
+                self.tlb_translate(vaddr, access)
+            }
+            //XKPHYS
+            //unmapped, bits 61..59 pick the cache algorithm. the vr4300 only has 32 physical address
+            //bits, anything set in between is out of range
+            0x8000_0000_0000_0000..=0xBFFF_FFFF_FFFF_FFFF if level == 0 => {
+                if vaddr & 0x07FF_FFFF_0000_0000 != 0 {
+                    return address_error();
+                }
+                Ok((vaddr as u32, (vaddr >> 59) & 0b111 != 2))
+            }
+            //XKSEG
+            0xC000_0000_0000_0000..=0xC000_00FF_7FFF_FFFF if level == 0 => {
+                self.tlb_translate(vaddr, access)
+            }
+            //KSEG0
+            //Config.K0 picks the cache algorithm, 2 is uncached
+            0xFFFF_FFFF_8000_0000..=0xFFFF_FFFF_9FFF_FFFF if level == 0 => {
+                Ok(((vaddr as u32) - 0x8000_0000, k0 != 2))
+            }
+            //KSEG1
+            0xFFFF_FFFF_A000_0000..=0xFFFF_FFFF_BFFF_FFFF if level == 0 => {
+                Ok(((vaddr as u32) - 0xA000_0000, false))
+            }
+            //KSSEG
+            0xFFFF_FFFF_C000_0000..=0xFFFF_FFFF_DFFF_FFFF if level <= 1 => {
+                self.tlb_translate(vaddr, access)
+            }
+            //KSEG3
+            0xFFFF_FFFF_E000_0000..=0xFFFF_FFFF_FFFF_FFFF if level == 0 => {
+                self.tlb_translate(vaddr, access)
+            }
+            //the holes between the 64 bit regions, and segments above our privilege level
+            _ => address_error(),
+        }
+    }
+
+    //looks a mapped address up in the tlb, turning a miss into the matching tlb exception
+    fn tlb_translate(&self, vaddr: u64, access: MemAccess) -> Result<(u32, bool), ExecutionError> {
+        let cpu = self.cpu.borrow();
+        let asid = cpu.cop0.EntryHi as u8;
+        let write = access == MemAccess::Store;
+
+        match cpu
+            .tlb
+            .translate(vaddr, asid, cpu.is_64bit_addressing(), write)
+        {
+            Ok(translated) => Ok(translated),
+            Err(miss) => {
+                let refill = miss == TlbMiss::Refill;
+                let exc = match (miss, access) {
+                    (TlbMiss::Modified, _) => Exception::TlbModification { vaddr },
+                    (_, MemAccess::Store) => Exception::TlbStore { vaddr, refill },
+                    (_, _) => Exception::TlbLoad { vaddr, refill },
+                };
+                Err(ExecutionError::Exception(exc))
+            }
         }
     }
 
@@ -553,6 +1762,14 @@ impl System {
 0xC0000000 	0xDFFFFFFF 	KSSEG 	Kernel supervisor segment, TLB mapped
 0xE0000000 	0xFFFFFFFF 	KSEG3 	Kernel segment 3, TLB mapped*/
 
+//64 bit virtual map (KX/SX/UX)
+/*
+0x0000000000000000 	0x000000FFFFFFFFFF 	XKUSEG 	User segment, TLB mapped
+0x4000000000000000 	0x400000FFFFFFFFFF 	XKSSEG 	Supervisor segment, TLB mapped
+0x8000000000000000 	0xBFFFFFFFFFFFFFFF 	XKPHYS 	Directly mapped, cache algorithm in bits 61..59
+0xC000000000000000 	0xC00000FF7FFFFFFF 	XKSEG 	Kernel segment, TLB mapped
+0xFFFFFFFF80000000 	0xFFFFFFFFFFFFFFFF 	CKSEG* 	The 32 bit kernel segments, sign extended*/
+
 //physical map
 /*
 Bus / Device 	Address Range 	Name 	Description
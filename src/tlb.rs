@@ -0,0 +1,141 @@
+//the vr4300's joint tlb
+//32 entries, each one maps an even/odd pair of pages. the page size is set per entry by its PageMask
+//everything in here is in terms of the raw cop0 register formats, so TLBR/TLBW are just copies
+
+//EntryLo bits
+const ENTRYLO_PFN: u64 = 0x3FFF_FFC0;
+const ENTRYLO_C: u64 = 0x38;
+const ENTRYLO_D: u64 = 0x4;
+const ENTRYLO_V: u64 = 0x2;
+const ENTRYLO_G: u64 = 0x1;
+
+//EntryHi bits
+const ENTRYHI_R: u64 = 0xC000_0000_0000_0000;
+const ENTRYHI_VPN2: u64 = 0x0000_00FF_FFFF_E000;
+const ENTRYHI_ASID: u64 = 0xFF;
+
+#[derive(Default, Clone, Copy, Debug)]
+pub struct TlbEntry {
+    pub page_mask: u64,
+    //R, VPN2 and ASID. G lives on its own since its the AND of both EntryLo G bits
+    pub entry_hi: u64,
+    pub entry_lo0: u64,
+    pub entry_lo1: u64,
+    pub global: bool,
+}
+
+impl TlbEntry {
+    //bits of a virtual address that are offset within one page of this entry
+    fn offset_mask(&self) -> u64 {
+        (self.page_mask >> 1) | 0xFFF
+    }
+
+    //the bits that have to match between a vaddr and VPN2. R only counts in 64 bit mode
+    fn vpn_mask(&self, is_64bit: bool) -> u64 {
+        let vpn = if is_64bit {
+            ENTRYHI_R | ENTRYHI_VPN2
+        } else {
+            ENTRYHI_VPN2 & 0xFFFF_FFFF
+        };
+        vpn & !self.page_mask
+    }
+
+    fn matches(&self, vaddr: u64, asid: u8, is_64bit: bool) -> bool {
+        let mask = self.vpn_mask(is_64bit);
+        (vaddr & mask) == (self.entry_hi & mask)
+            && (self.global || (self.entry_hi & ENTRYHI_ASID) as u8 == asid)
+    }
+}
+
+//why a translation didnt go through, the caller turns these into the matching exception
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TlbMiss {
+    //no entry matched at all
+    Refill,
+    //an entry matched but the page isnt valid
+    Invalid,
+    //a store hit a valid page that isnt dirty (writable)
+    Modified,
+}
+
+#[derive(Default)]
+pub struct Tlb {
+    pub entries: [TlbEntry; 32],
+}
+
+impl Tlb {
+    //returns the physical address and whether the page is cacheable
+    pub fn translate(
+        &self,
+        vaddr: u64,
+        asid: u8,
+        is_64bit: bool,
+        write: bool,
+    ) -> Result<(u32, bool), TlbMiss> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.matches(vaddr, asid, is_64bit))
+            .ok_or(TlbMiss::Refill)?;
+
+        //the bit just above the page offset picks the even or odd page of the pair
+        let offset_mask = entry.offset_mask();
+        let lo = if vaddr & (offset_mask + 1) != 0 {
+            entry.entry_lo1
+        } else {
+            entry.entry_lo0
+        };
+
+        if lo & ENTRYLO_V == 0 {
+            return Err(TlbMiss::Invalid);
+        }
+        if write && lo & ENTRYLO_D == 0 {
+            return Err(TlbMiss::Modified);
+        }
+
+        let pfn_base = ((lo & ENTRYLO_PFN) >> 6) << 12;
+        let phys = (pfn_base & !offset_mask) | (vaddr & offset_mask);
+        //C = 2 is uncached, everything else caches
+        let cached = (lo & ENTRYLO_C) >> 3 != 2;
+
+        Ok((phys as u32, cached))
+    }
+
+    //TLBP: index of the entry matching EntryHi, if there is one
+    pub fn probe(&self, entry_hi: u64, is_64bit: bool) -> Option<usize> {
+        let asid = (entry_hi & ENTRYHI_ASID) as u8;
+        self.entries
+            .iter()
+            .position(|e| e.matches(entry_hi, asid, is_64bit))
+    }
+
+    //TLBR: (PageMask, EntryHi, EntryLo0, EntryLo1) for the entry at index
+    pub fn read(&self, index: usize) -> (u64, u64, u64, u64) {
+        let e = &self.entries[index & 0x1F];
+        let g = e.global as u64;
+        (
+            e.page_mask,
+            e.entry_hi & !e.page_mask,
+            (e.entry_lo0 & !ENTRYLO_G) | g,
+            (e.entry_lo1 & !ENTRYLO_G) | g,
+        )
+    }
+
+    //TLBWI/TLBWR
+    pub fn write(
+        &mut self,
+        index: usize,
+        page_mask: u64,
+        entry_hi: u64,
+        entry_lo0: u64,
+        entry_lo1: u64,
+    ) {
+        self.entries[index & 0x1F] = TlbEntry {
+            page_mask,
+            entry_hi: entry_hi & (ENTRYHI_R | ENTRYHI_VPN2 | ENTRYHI_ASID),
+            entry_lo0: entry_lo0 & !ENTRYLO_G,
+            entry_lo1: entry_lo1 & !ENTRYLO_G,
+            global: (entry_lo0 & entry_lo1 & ENTRYLO_G) != 0,
+        };
+    }
+}